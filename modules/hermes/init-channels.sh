@@ -8,11 +8,12 @@ apt-get update && apt-get install jq yq curl -y && apt-get clean
 cp /root/bin/* /usr/local/bin
 cd /root
 
-# this file contains a list of chains to be connected; the first chain is the 'hub' chain that will be connected to all other chains
+# this file contains the list of chains to be connected and the links (channels) to create between them
 # it is generated from the provided config file by the 'nibc-forge create' command
-# for each chain, lists the node hostname, relayer key, and chain type
+# for each chain, lists the node hostname, relayer key, chain type, and the commands to query its fee denom and import the relayer key
+# for each link, lists the hostnames of the two chains, their port ids, the channel ordering and version
 echo "Reading chainlist.json..."
-CHAIN_HOSTS=$(cat /root/chainlist.json | jq -r .[].hostname)
+CHAIN_HOSTS=$(cat /root/chainlist.json | jq -r .chains[].hostname)
 
 # for each chain, verify that chain has reached block 3 and get the chain id. if not, skip the chain (timeout after 2 minutes)
 CHAIN_IDS=""
@@ -52,21 +53,15 @@ for HOSTNAME in $CHAIN_HOSTS; do
   CHAIN_ID=${CHAIN_IDS_ARRAY[$i]}
 
   if [[ "${CHAIN_ID}" == "none" ]]; then
+    ((i++))
     continue
   elif [[ -z "${CHAIN_ID}" ]]; then
     break
   fi
 
-  # query the node for the fee token denom
-  CHAIN_TYPE=$(cat /root/chainlist.json | jq -r .[$i].type)
-  if [[ "${CHAIN_TYPE}" == "namada" ]]; then
-    nam_borsh=$(curl -s curl -s "http://$HOSTNAME:26657/abci_query?path=\"/shell/native_token\"&prove=false" | jq -r .result.response.value)
-    DENOM=$(addr-decode $nam_borsh)
-  elif [[ "${CHAIN_TYPE}" == "gaia" ]]; then
-    DENOM=$(curl -s "http://$HOSTNAME:1317/feemarket/v1/params" | jq -r .params.fee_denom)
-  elif [[ "${CHAIN_TYPE}" == "osmosis" ]]; then
-    DENOM=$(curl -s "http://$HOSTNAME:1317/osmosis/txfees/v1beta1/base_denom" | jq -r .base_denom)
-  fi
+  # query the node for the fee token denom, using the query for the chain type given in chainlist.json
+  DENOM_QUERY=$(cat /root/chainlist.json | jq -r .chains[$i].denom_query)
+  DENOM=$(eval "$DENOM_QUERY")
 
   # update the hermes config
   sed -i \
@@ -76,65 +71,110 @@ for HOSTNAME in $CHAIN_HOSTS; do
     -e "s|KEY_${i}|relayer${i}|" \
     /root/.hermes/config.toml
   
-  # add the relayer key
-  RELAYER_KEY=$(cat /root/chainlist.json | jq -r .[$i].key)
-  if [[ "${CHAIN_TYPE}" == "namada" ]]; then
-    # namada supports both raw keys and mnemonics. if the relayer_key contains a space, it's assumed to be a mnemonic
-    if [[ "$RELAYER_KEY" =~ \  ]]; then
-      echo $RELAYER_KEY | namadaw --pre-genesis derive --alias relayer${i} --unsafe-dont-encrypt
-    else
-      namadaw --pre-genesis add --value $RELAYER_KEY --alias relayer${i} --unsafe-dont-encrypt
-    fi
-    hermes keys add --chain $CHAIN_ID --key-file /root/.local/share/namada/pre-genesis/wallet.toml
-  else
-    # cosmos chains only support mnemonics
-    echo "$RELAYER_KEY" | hermes keys add --chain $CHAIN_ID --mnemonic-file /dev/stdin
-  fi
-  
-  # if the chain type is namada, run this dummy query to force the MASP params download ahead of time
-  # otherwise, it may appear unexpectedly in later log output, causing invalid json structure
-  if [[ "${CHAIN_TYPE}" == "namada" ]]; then
-    hermes query channels --chain $CHAIN_ID
-  fi
+  # add the relayer key, using the import commands for the chain type given in chainlist.json
+  RELAYER_KEY=$(cat /root/chainlist.json | jq -r .chains[$i].key)
+  KEY_NAME="relayer${i}"
+  KEY_IMPORT=$(cat /root/chainlist.json | jq -r .chains[$i].key_import)
+  eval "$KEY_IMPORT"
 
   ((i++))
 done
 
 CHAIN_IDS_ARRAY=($CHAIN_IDS)
+CHAIN_HOSTS_ARRAY=($CHAIN_HOSTS)
 num_chains=${#CHAIN_IDS_ARRAY[@]}
 
-# depending on the value of the TOPOLOGY variable, connect the chains either in a mesh or hub-and-spoke manner
+# create a channel between two chains (given by their index in chainlist.json) and save the result to a json file
+# args: chain a index, chain b index, a port, b port, ordering, version
+create_channel() {
+  local a_chain=${CHAIN_IDS_ARRAY[$1]}
+  local b_chain=${CHAIN_IDS_ARRAY[$2]}
+  local a_port=${3:-transfer}
+  local b_port=${4:-transfer}
+  local ordering=${5:-unordered}
+  local version=$6
+
+  if [[ "$a_chain" == "none" || "$b_chain" == "none" ]]; then
+    echo "Skipping channel between ${CHAIN_HOSTS_ARRAY[$1]} and ${CHAIN_HOSTS_ARRAY[$2]}; chain not available"
+    # save a skipped result so that the channel is not waited for; the file is named after the hostnames, as the chain
+    # ids are not known
+    local unavailable=()
+    [[ "$a_chain" == "none" ]] && unavailable+=("${CHAIN_HOSTS_ARRAY[$1]}")
+    [[ "$b_chain" == "none" ]] && unavailable+=("${CHAIN_HOSTS_ARRAY[$2]}")
+    jq -n \
+      --arg a_hostname "${CHAIN_HOSTS_ARRAY[$1]}" --arg b_hostname "${CHAIN_HOSTS_ARRAY[$2]}" \
+      --arg a_chain_id "$a_chain" --arg b_chain_id "$b_chain" \
+      --arg a_port "$a_port" --arg b_port "$b_port" \
+      --arg result "chain not available: ${unavailable[*]}" \
+      '{status: "skipped", result: $result, a_hostname: $a_hostname, b_hostname: $b_hostname, a_chain_id: $a_chain_id, b_chain_id: $b_chain_id, a_port: $a_port, b_port: $b_port}' \
+      > "/root/.hermes/skipped_${CHAIN_HOSTS_ARRAY[$1]}_${CHAIN_HOSTS_ARRAY[$2]}@${a_port}.json"
+    return
+  fi
+
+  # files are named after the chain ids; links on other ports than transfer get the port id of chain a appended
+  local name="${a_chain}_${b_chain}"
+  if [[ "$a_port" != "transfer" || "$b_port" != "transfer" ]]; then
+    name+="@${a_port}"
+  fi
+  local log_file="/root/.hermes/${name}.log"
+  local json_file="/root/.hermes/${name}.json"
+
+  local version_args=()
+  if [[ -n "$version" ]]; then
+    version_args=(--channel-version "$version")
+  fi
+
+  echo "Creating channel between ${a_chain}:${a_port} and ${b_chain}:${b_port}... this may take 3 to 5 minutes"
+  hermes --json create channel --a-chain $a_chain --b-chain $b_chain --a-port $a_port --b-port $b_port --order $ordering "${version_args[@]}" --new-client-connection --yes > $log_file
+  cat $log_file
+
+  # save the last json object in the log file (which holds the channel creation result) to a json file for easy reference later,
+  # along with the hostnames and ports of both chains
+  cat $log_file | jq -s \
+    --arg a_hostname "${CHAIN_HOSTS_ARRAY[$1]}" --arg b_hostname "${CHAIN_HOSTS_ARRAY[$2]}" \
+    --arg a_port "$a_port" --arg b_port "$b_port" \
+    '.[-1] + {a_hostname: $a_hostname, b_hostname: $b_hostname, a_port: $a_port, b_port: $b_port}' > $json_file
+}
+
+# find the index of a chain in chainlist.json by its hostname
+chain_index() {
+  for ((k=0; k<num_chains; k++)); do
+    if [[ "${CHAIN_HOSTS_ARRAY[$k]}" == "$1" ]]; then
+      echo $k
+      return
+    fi
+  done
+}
+
+num_links=$(cat /root/chainlist.json | jq '.links | length')
+
+if [[ "$num_links" -gt 0 ]]; then
+  # create exactly the links listed in the deployment config
+  for ((l=0; l<num_links; l++)); do
+    link=$(cat /root/chainlist.json | jq -c .links[$l])
+    a=$(chain_index "$(echo $link | jq -r .a_chain)")
+    b=$(chain_index "$(echo $link | jq -r .b_chain)")
+    create_channel $a $b \
+      "$(echo $link | jq -r .a_port)" \
+      "$(echo $link | jq -r .b_port)" \
+      "$(echo $link | jq -r .ordering)" \
+      "$(echo $link | jq -r '.version // empty')"
+  done
+
+# otherwise, depending on the value of the TOPOLOGY variable, connect the chains either in a mesh or hub-and-spoke manner
 # mesh: establish ibc connections between all chains
 # hub: (default) establish ibc connections between the first chain and all other chains
-
-if [ "$TOPOLOGY" == "mesh" ]; then
+elif [ "$TOPOLOGY" == "mesh" ]; then
   # initialize channels between all chains (mesh topology)
   for ((i=0; i<num_chains-1; i++)); do
     for ((j=i+1; j<num_chains; j++)); do
-      echo "Creating channel between ${CHAIN_IDS_ARRAY[i]} and ${CHAIN_IDS_ARRAY[j]}... this may take 3 to 5 minutes"
-      log_file="/root/.hermes/${CHAIN_IDS_ARRAY[i]}_${CHAIN_IDS_ARRAY[j]}.log"
-      json_file="/root/.hermes/${CHAIN_IDS_ARRAY[i]}_${CHAIN_IDS_ARRAY[j]}.json"
-      
-      hermes --json create channel --a-chain ${CHAIN_IDS_ARRAY[i]} --b-chain ${CHAIN_IDS_ARRAY[j]} --a-port transfer --b-port transfer --new-client-connection --yes > $log_file
-      cat $log_file
-
-      # save the last json object in the log file (which holds the channel creation result) to a json file for easy reference later
-      cat $log_file | jq -s '.[-1]' > $json_file
+      create_channel $i $j
     done
   done
 
 else
   # initialize the channels between the first chain (chain 0) and all other chains (hub and spoke topology)
-  i=1
-  for CHAIN in "${CHAIN_IDS_ARRAY[@]:1}"; do
-    echo "Creating channel between ${CHAIN_IDS_ARRAY[0]} and ${CHAIN_IDS_ARRAY[i]}... this may take 3 to 5 minutes"
-    log_file="/root/.hermes/${CHAIN_IDS_ARRAY[0]}_${CHAIN_IDS_ARRAY[i]}.log"
-    json_file="/root/.hermes/${CHAIN_IDS_ARRAY[0]}_${CHAIN_IDS_ARRAY[i]}.json"
-    hermes --json create channel --a-chain ${CHAIN_IDS_ARRAY[0]} --b-chain ${CHAIN_IDS_ARRAY[i]} --a-port transfer --b-port transfer --new-client-connection --yes > $log_file
-    cat $log_file
-
-    # save the json result
-    cat $log_file | jq -s '.[-1]' > $json_file
-    ((i++))
+  for ((i=1; i<num_chains; i++)); do
+    create_channel 0 $i
   done
 fi
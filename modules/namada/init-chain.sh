@@ -53,13 +53,17 @@ cp -a /root/genesis /root/ammended-genesis
 echo "\n" >> /root/ammended-genesis/transactions.toml
 cat /root/signed-transactions.toml >> /root/ammended-genesis/transactions.toml
 
-# append genesis balances to the balances.toml
-{
-  echo "${addresses[validator]} = \"100000\""
-  echo "$EST_ADDRESS = \"100000\""
-  echo "${addresses[relayer]} = \"100000\""
-  echo "${addresses[faucet]} = \"400000000\""
-} >> /root/ammended-genesis/balances.toml
+# append genesis balances to the balances.toml, skipping any address that already has a NAM balance
+# (eg. when the genesis was seeded from a previous deployment's state with the genesis_state option)
+add_nam_balance() {
+  if ! awk -v addr="$1" '/^\[/ { in_nam = ($0 == "[token.nam]") } in_nam && $1 == addr { found = 1 } END { exit !found }' /root/ammended-genesis/balances.toml; then
+    echo "$1 = \"$2\"" >> /root/ammended-genesis/balances.toml
+  fi
+}
+add_nam_balance "${addresses[validator]}" "100000"
+add_nam_balance "$EST_ADDRESS" "100000"
+add_nam_balance "${addresses[relayer]}" "100000"
+add_nam_balance "${addresses[faucet]}" "400000000"
 
 # extract the tx and vp checksums from the checksums.json file
 TX_CHECKSUMS=$(jq -r 'to_entries[] | select(.key | startswith("tx")) | .value' /root/ammended-genesis/wasm/checksums.json | sed 's/.*\.\(.*\)\..*/"\1"/' | paste -sd "," -)
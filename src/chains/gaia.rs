@@ -0,0 +1,26 @@
+use super::{validate_mnemonic, ChainType, COSMOS_KEY_IMPORT};
+
+pub struct Gaia;
+
+impl ChainType for Gaia {
+    fn name(&self) -> &'static str {
+        "gaia"
+    }
+
+    fn account_prefix(&self) -> &'static str {
+        "cosmos"
+    }
+
+    fn validate_relayer_key(&self, key: &str) -> Result<(), String> {
+        // Hermes only supports mnemonics for Cosmos SDK chains
+        validate_mnemonic(key)
+    }
+
+    fn fee_denom_query(&self) -> &'static str {
+        r#"curl -s "http://$HOSTNAME:1317/feemarket/v1/params" | jq -r .params.fee_denom"#
+    }
+
+    fn relayer_key_import(&self) -> &'static str {
+        COSMOS_KEY_IMPORT
+    }
+}
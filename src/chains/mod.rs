@@ -0,0 +1,48 @@
+pub mod gaia;
+pub mod namada;
+pub mod osmosis;
+
+use std::path::{Path, PathBuf};
+use crate::utils::HERMES_TEMPLATE_DIR;
+
+/// Behaviour specific to a kind of chain module. Adding support for a new chain only requires a new implementation
+/// of this trait and a variant in `config::ModuleType`.
+///
+/// The shell snippets are written to the Hermes `chainlist.json` and evaluated by its `init-channels.sh` script, with
+/// `$HOSTNAME`, `$CHAIN_ID`, `$RELAYER_KEY` and `$KEY_NAME` set for the chain being configured.
+pub trait ChainType {
+    /// Value of the `type` key in the deployment config
+    fn name(&self) -> &'static str;
+
+    /// Hermes template used when the module does not specify one
+    fn default_hermes_template(&self) -> PathBuf {
+        Path::new(HERMES_TEMPLATE_DIR).join(format!("{}.toml", self.name()))
+    }
+
+    /// Bech32 prefix of account addresses, always used for the Hermes `account_prefix` setting
+    fn account_prefix(&self) -> &'static str;
+
+    /// Check that the relayer key is in a format Hermes can import for this chain
+    fn validate_relayer_key(&self, key: &str) -> Result<(), String>;
+
+    /// Shell command printing the fee token denom of the node at `$HOSTNAME`
+    fn fee_denom_query(&self) -> &'static str;
+
+    /// Shell commands adding `$RELAYER_KEY` to Hermes under the name `$KEY_NAME`
+    fn relayer_key_import(&self) -> &'static str;
+}
+
+/// Shell commands adding a mnemonic relayer key to Hermes, shared by the Cosmos SDK chains
+pub const COSMOS_KEY_IMPORT: &str =
+    r#"echo "$RELAYER_KEY" | hermes keys add --chain $CHAIN_ID --key-name $KEY_NAME --mnemonic-file /dev/stdin"#;
+
+pub fn validate_mnemonic(key: &str) -> Result<(), String> {
+    let words: Vec<&str> = key.split_whitespace().collect();
+    if ![12, 15, 18, 21, 24].contains(&words.len()) {
+        return Err(format!("mnemonic must have 12, 15, 18, 21 or 24 words, found {}", words.len()));
+    }
+    if !words.iter().all(|word| word.chars().all(|c| c.is_ascii_lowercase())) {
+        return Err("mnemonic words must only contain lowercase letters".to_string());
+    }
+    Ok(())
+}
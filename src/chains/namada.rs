@@ -0,0 +1,45 @@
+use super::{validate_mnemonic, ChainType};
+
+pub struct Namada;
+
+impl ChainType for Namada {
+    fn name(&self) -> &'static str {
+        "namada"
+    }
+
+    fn account_prefix(&self) -> &'static str {
+        ""
+    }
+
+    fn validate_relayer_key(&self, key: &str) -> Result<(), String> {
+        // Namada accepts either a mnemonic or a raw private key: a key scheme byte (00 for ed25519, 01 for secp256k1)
+        // followed by the 32 byte secret key, hex encoded
+        if key.contains(char::is_whitespace) {
+            return validate_mnemonic(key);
+        }
+        let is_raw_key = key.len() == 66
+            && (key.starts_with("00") || key.starts_with("01"))
+            && key.chars().all(|c| c.is_ascii_hexdigit());
+        if !is_raw_key {
+            return Err("expected a mnemonic or a hex encoded private key starting with 00 or 01".to_string());
+        }
+        Ok(())
+    }
+
+    fn fee_denom_query(&self) -> &'static str {
+        // The native token address is returned borsh encoded and decoded with the addr-decode tool of the Hermes module
+        r#"addr-decode $(curl -s "http://$HOSTNAME:26657/abci_query?path=\"/shell/native_token\"&prove=false" | jq -r .result.response.value)"#
+    }
+
+    fn relayer_key_import(&self) -> &'static str {
+        // Keys containing a space are assumed to be mnemonics. Afterwards, run a dummy query to force the MASP params
+        // download ahead of time, otherwise it may appear unexpectedly in later log output, causing invalid json structure
+        r#"if [[ "$RELAYER_KEY" =~ \  ]]; then
+  echo $RELAYER_KEY | namadaw --pre-genesis derive --alias $KEY_NAME --unsafe-dont-encrypt
+else
+  namadaw --pre-genesis add --value $RELAYER_KEY --alias $KEY_NAME --unsafe-dont-encrypt
+fi
+hermes keys add --chain $CHAIN_ID --key-name $KEY_NAME --key-file /root/.local/share/namada/pre-genesis/wallet.toml
+hermes query channels --chain $CHAIN_ID"#
+    }
+}
@@ -0,0 +1,26 @@
+use super::{validate_mnemonic, ChainType, COSMOS_KEY_IMPORT};
+
+pub struct Osmosis;
+
+impl ChainType for Osmosis {
+    fn name(&self) -> &'static str {
+        "osmosis"
+    }
+
+    fn account_prefix(&self) -> &'static str {
+        "osmo"
+    }
+
+    fn validate_relayer_key(&self, key: &str) -> Result<(), String> {
+        // Hermes only supports mnemonics for Cosmos SDK chains
+        validate_mnemonic(key)
+    }
+
+    fn fee_denom_query(&self) -> &'static str {
+        r#"curl -s "http://$HOSTNAME:1317/osmosis/txfees/v1beta1/base_denom" | jq -r .base_denom"#
+    }
+
+    fn relayer_key_import(&self) -> &'static str {
+        COSMOS_KEY_IMPORT
+    }
+}
@@ -1,77 +1,60 @@
+use crate::deployment::{Deployment, DEPLOYMENT_LABEL};
 use crate::error::AppError;
-use crate::utils::deployment_compose_files;
+use crate::utils::{confirm, labeled_resources, remove_resources, RESOURCE_TYPES};
+use colored::Colorize;
+use std::fs;
 use std::path::Path;
-use std::process::Command;
 
-pub fn handle_clean(args: crate::DeploymentArgs) -> Result<(), AppError> {
+pub fn handle_clean(args: crate::CleanArgs) -> Result<(), AppError> {
     println!(
         "Cleaning deployment in directory: {}",
         args.deployment_dir
     );
-    
-    let deployment_dir = Path::new(&args.deployment_dir);
-    let project_name = deployment_dir.file_name().unwrap().to_str().unwrap();
 
-    // Collect all docker-compose.yml files from subdirectories
-    let compose_files = deployment_compose_files(deployment_dir)?;
+    let deployment = Deployment::load(Path::new(&args.deployment_dir))?;
+    deployment.check_project()?;
 
-    // First, run docker-compose down
-    let mut down_command = Command::new("docker");
-    down_command.arg("compose");
-    for compose_file in &compose_files {
-        down_command.arg("-f").arg(compose_file.to_str().unwrap());
+    // Only resources labelled by nibc-forge as belonging to this deployment are removed
+    let label = format!("{}={}", DEPLOYMENT_LABEL, deployment.project);
+    let mut resources = Vec::new();
+    for resource_type in RESOURCE_TYPES {
+        for name in labeled_resources(resource_type, &label)? {
+            resources.push((resource_type, name));
+        }
     }
-    down_command
-        .arg("--project-name")
-        .arg(project_name)
-        .arg("down")
-        .arg("--volumes");
-
-    let status = down_command.status().map_err(AppError::Io)?;
 
-    if !status.success() {
-        println!("docker-compose down failed with status code: {:?}", status.code());
-        println!("You may need to manually stop and remove the containers associated with this deployment.");
+    if resources.is_empty() {
+        println!("No containers, networks, volumes or images found for project: {}", deployment.project);
+    } else {
+        println!("\n{}", "The following resources will be removed:".yellow().bold());
+        for (resource_type, name) in &resources {
+            println!("{:<10} {}", resource_type, name);
+        }
+    }
+    if args.purge {
+        println!("\n{} {}", "The deployment directory will be deleted:".yellow().bold(), args.deployment_dir);
+    }
+    if resources.is_empty() && !args.purge {
+        return Ok(());
     }
 
-    // Manually clean all containers, networks, and volumes with label matching the project name
-    clean_resources("container", project_name)?;
-    clean_resources("network", project_name)?;
-    clean_resources("volume", project_name)?;
-
-    Ok(())
-}
-
-fn clean_resources(resource_type: &str, project_name: &str) -> Result<(), AppError> {
-    println!("Cleaning {} resources for project: {}", resource_type, project_name);
-
-    let label_filter = format!("label=com.docker.compose.project={}", project_name);
-    let resources_output = Command::new("docker")
-        .arg(resource_type)
-        .arg("ls")
-        .arg("--filter")
-        .arg(&label_filter)
-        .arg("--format")
-        .arg("{{.ID}}") // Get only the resource IDs
-        .output()
-        .map_err(AppError::Io)?;
-
-    let resource_ids_output = String::from_utf8_lossy(&resources_output.stdout);
-    let resource_ids = resource_ids_output.lines().collect::<Vec<_>>();
+    if !args.yes && !confirm("\nContinue?")? {
+        println!("Clean aborted; nothing was removed.");
+        return Ok(());
+    }
 
-    for id in resource_ids {
-        let status = Command::new("docker")
-            .arg(resource_type)
-            .arg("rm")
-            .arg("-f") // Force removal
-            .arg(id)
-            .status()
-            .map_err(AppError::Io)?;
+    let failed = remove_resources(&resources)?;
+    if failed > 0 {
+        return Err(AppError::DockerCommand(format!(
+            "Failed to remove {} resource(s); the deployment directory was kept",
+            failed
+        )));
+    }
 
-        if !status.success() {
-            println!("Failed to remove {}: {}", resource_type, id);
-        }
+    if args.purge {
+        fs::remove_dir_all(&args.deployment_dir).map_err(AppError::Io)?;
+        println!("Deleted deployment directory: {}", args.deployment_dir);
     }
 
     Ok(())
-}
\ No newline at end of file
+}
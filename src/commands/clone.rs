@@ -0,0 +1,234 @@
+use crate::commands::create::copy_dir_recursively;
+use crate::commands::snapshot::{restore_snapshot, snapshot_dir, take_snapshot, SNAPSHOTS_DIR};
+use crate::commands::start::handle_start;
+use crate::compose::{allocate_port, labels_override, port_variables};
+use crate::deployment::{
+    generate_project_id, DeployedModule, Deployment, DEPLOYMENT_LABEL, DIR_LABEL, LABELS_COMPOSE_FILE,
+};
+use crate::error::AppError;
+use crate::utils::{container_state, print_table};
+use colored::Colorize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+pub fn handle_clone(args: crate::CloneArgs) -> Result<(), AppError> {
+    println!("Cloning deployment in directory {} to {}", args.from, args.to);
+
+    let source = Deployment::load(Path::new(&args.from))?;
+    source.check_project()?;
+
+    let to = Path::new(&args.to);
+    if to.exists() {
+        return Err(AppError::InvalidConfig(format!("Directory {} already exists", args.to)));
+    }
+    let clone_name = to
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| AppError::InvalidConfig(format!("Invalid deployment directory: {}", args.to)))?;
+    let parent_dir = match to.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    // Containers named with a fixed container_name would be the same for both deployments
+    let container_prefix = format!("{}-", source.project);
+    for module in &source.modules {
+        if let Some(service) = module.services.iter().find(|service| !service.container.starts_with(&container_prefix)) {
+            return Err(AppError::InvalidConfig(format!(
+                "Service {} of {} has a fixed container name ({}), which cannot be used by two deployments",
+                service.name, module.name, service.container
+            )));
+        }
+    }
+
+    // Only ports set by a variable can be moved; the clone could never start next to the source with any other
+    let mut fixed_ports = Vec::new();
+    for module in &source.modules {
+        let variables = host_port_variables(&source, module)?;
+        for service in &module.services {
+            for host_port in service.ports.values() {
+                if !variables.values().any(|port| port == host_port) {
+                    fixed_ports.push(format!("{} of {}/{}", host_port, module.name, service.name));
+                }
+            }
+        }
+    }
+    if !fixed_ports.is_empty() {
+        return Err(AppError::InvalidConfig(format!(
+            "Host ports not set by a *_PORT variable cannot be moved to free ports in the clone: {}",
+            fixed_ports.join(", ")
+        )));
+    }
+
+    fs::create_dir_all(parent_dir).map_err(AppError::Io)?;
+    let absolute_dir = parent_dir.canonicalize().map_err(AppError::Io)?.join(clone_name);
+    let project = generate_project_id(&absolute_dir);
+
+    // The state of the source is copied through a snapshot, which stops it for a moment
+    let was_running = source
+        .modules
+        .iter()
+        .flat_map(|module| module.services.iter())
+        .any(|service| container_state(&service.container).is_some_and(|state| state.starts_with("running")));
+    let snapshot_name = format!("clone-{}", project);
+    let snapshot = take_snapshot(&source, &snapshot_name, &project, &absolute_dir)?;
+
+    let copied = copy_deployment(&source, to, &snapshot_name);
+    // The snapshot images stay, as the clone runs from them; they are labelled as the clone's
+    fs::remove_dir_all(snapshot_dir(&source, &snapshot_name)).map_err(AppError::Io)?;
+    if was_running {
+        println!("\nStarting the source deployment again");
+        handle_start(crate::StartArgs {
+            deployment_dir: args.from.clone(),
+            modules: Vec::new(),
+            timeout: args.timeout,
+        })?;
+    }
+    if let Err(err) = copied {
+        let _ = fs::remove_dir_all(to);
+        return Err(err);
+    }
+
+    let mut clone = Deployment::load(to)?;
+    clone.project = project;
+    let port_changes = match remap_clone(&mut clone, &source, &absolute_dir.to_string_lossy()) {
+        Ok(port_changes) => port_changes,
+        Err(err) => {
+            let _ = fs::remove_dir_all(to);
+            return Err(err);
+        }
+    };
+
+    println!("\n{}", "Host ports of the clone:".yellow().bold());
+    print_table(&["MODULE", "VARIABLE", "SOURCE PORT", "CLONE PORT"], &port_changes);
+
+    println!("\nStarting the clone");
+    restore_snapshot(&clone, &snapshot, args.timeout)?;
+    // The clone now runs from the loaded images and volumes, so the tarballs are not needed anymore
+    fs::remove_dir_all(snapshot_dir(&clone, &snapshot_name)).map_err(AppError::Io)?;
+
+    println!(
+        "\n{} {} (project {})",
+        "Deployment cloned to".green().bold(),
+        args.to,
+        clone.project
+    );
+    Ok(())
+}
+
+// Move the copied deployment to its own project and free host ports, and write its manifest. Returns the module,
+// variable, old and new port of every remapped port
+fn remap_clone(clone: &mut Deployment, source: &Deployment, absolute_dir: &str) -> Result<Vec<Vec<String>>, AppError> {
+    // The ports of the source are taken, even while it is stopped
+    let mut used_ports: HashSet<u16> = source
+        .modules
+        .iter()
+        .flat_map(|module| module.ports.values().copied())
+        .collect();
+
+    let variables = clone
+        .modules
+        .iter()
+        .map(|module| host_port_variables(clone, module))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut port_changes = Vec::new();
+    for (module, variables) in clone.modules.iter_mut().zip(variables) {
+        let module_dir = clone.dir.join(&module.dir);
+        let mut remapped = Vec::new();
+        for (name, port) in module.ports.iter_mut().filter(|(name, _)| variables.contains_key(*name)) {
+            let new_port = allocate_port(*port, &used_ports).ok_or_else(|| {
+                AppError::InvalidConfig(format!("No free host port available for {} of module {}", name, module.name))
+            })?;
+            used_ports.insert(new_port);
+            set_env_variable(&module_dir.join(".env"), name, &new_port.to_string())?;
+            port_changes.push(vec![module.name.clone(), name.clone(), port.to_string(), new_port.to_string()]);
+            remapped.push((*port, new_port));
+            *port = new_port;
+        }
+
+        for service in module.services.iter_mut() {
+            service.container = format!("{}-{}", clone.project, &service.container[source.project.len() + 1..]);
+            for host_port in service.ports.values_mut() {
+                if let Some((_, new_port)) = remapped.iter().find(|(old, _)| old == host_port) {
+                    *host_port = *new_port;
+                }
+            }
+        }
+
+        let compose_file = module_dir.join("docker-compose.yml");
+        if compose_file.exists() {
+            let content = fs::read_to_string(&compose_file).map_err(AppError::Io)?;
+            let labels = labels_override(&content, &[(DEPLOYMENT_LABEL, &clone.project), (DIR_LABEL, absolute_dir)])?;
+            fs::write(module_dir.join(LABELS_COMPOSE_FILE), labels).map_err(AppError::Io)?;
+        }
+    }
+
+    // A project name given in the spec is the source's; the clone runs under its own
+    if clone.spec.contains_key("name") {
+        clone.spec.insert("name".to_string(), toml::Value::String(clone.project.clone()));
+    }
+
+    clone.write()?;
+    Ok(port_changes)
+}
+
+// Copy the directory of a deployment, leaving out its snapshots except the one the clone is started from
+fn copy_deployment(source: &Deployment, to: &Path, snapshot_name: &str) -> Result<(), AppError> {
+    fs::create_dir_all(to).map_err(AppError::Io)?;
+    for entry in fs::read_dir(&source.dir).map_err(AppError::Io)? {
+        let entry = entry.map_err(AppError::Io)?;
+        let path = entry.path();
+        if entry.file_name() == SNAPSHOTS_DIR {
+            continue;
+        }
+        if path.is_dir() {
+            copy_dir_recursively(&path.to_string_lossy(), &to.join(entry.file_name()))?;
+        } else {
+            fs::copy(&path, to.join(entry.file_name())).map_err(AppError::Io)?;
+        }
+    }
+    copy_dir_recursively(
+        &snapshot_dir(source, snapshot_name).to_string_lossy(),
+        &to.join(SNAPSHOTS_DIR).join(snapshot_name),
+    )
+}
+
+// The port variables of a module that only set the host side of its port mappings, with their ports
+fn host_port_variables(deployment: &Deployment, module: &DeployedModule) -> Result<BTreeMap<String, u16>, AppError> {
+    let compose_file = deployment.compose_file(module);
+    if !compose_file.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(&compose_file).map_err(AppError::Io)?;
+    let names: Vec<String> = port_variables(&content).into_iter().map(|(name, _)| name).collect();
+    Ok(module
+        .ports
+        .iter()
+        .filter(|(name, _)| names.contains(name))
+        .map(|(name, port)| (name.clone(), *port))
+        .collect())
+}
+
+// Set a variable in a .env file, replacing its line if it has one
+fn set_env_variable(path: &Path, name: &str, value: &str) -> Result<(), AppError> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    let prefix = format!("{}=", name);
+    let mut found = false;
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|line| {
+            if line.starts_with(&prefix) {
+                found = true;
+                format!("{}{}", prefix, value)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    if !found {
+        lines.push(format!("{}{}", prefix, value));
+    }
+    fs::write(path, lines.join("\n") + "\n").map_err(AppError::Io)
+}
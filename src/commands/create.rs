@@ -1,15 +1,16 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use colored::Colorize;
-use serde::{Deserialize, Serialize};
 use serde_json::json;
-use serde_yaml::{Value, to_string};
-use toml_edit::{value, DocumentMut, Item};
-use regex::Regex;
+use toml_edit::{table, value, Array, DocumentMut, Item};
 
-use crate::config::{Config, ModuleConfig};
-use crate::utils::HERMES_TEMPLATE_DIR;
+use crate::commands::dump_db::LedgerState;
+use crate::commands::snapshot::SNAPSHOTS_DIR;
+use crate::compose::{allocate_port, labels_override, compose_resources, env_file_content, port_available, port_variables, rename_services};
+use crate::config::{resolve_spec, Config, LinkConfig, ModuleConfig, ModuleType};
+use crate::deployment::{generate_project_id, valid_project_name, DeployedModule, DeployedService, Deployment, DEPLOYMENT_LABEL, DIR_LABEL, LABELS_COMPOSE_FILE, MANIFEST_FILE};
 use crate::error::AppError;
 
 pub fn handle_create(args: crate::CreateArgs) -> Result<(), AppError> {
@@ -18,35 +19,430 @@ pub fn handle_create(args: crate::CreateArgs) -> Result<(), AppError> {
         args.config_file, args.deployment_dir
     );
 
-    // Parse the config file
-    let config_content = fs::read_to_string(&args.config_file)
-        .map_err(|_| AppError::InvalidConfig("Failed to read config file".to_string()))?;
-    let config: Config = toml::from_str(&config_content)
-        .map_err(|_| AppError::InvalidConfig("Invalid config file format".to_string()))?;
+    let spec = resolve_spec(Path::new(&args.config_file))?;
+    let config = parse_config(spec.clone())?;
 
-
-    // Check if deployment dir exists
+    // An existing deployment is only replaced with --force, and never while it is running
     let deployment_dir = Path::new(&args.deployment_dir);
     if deployment_dir.exists() {
+        if !args.force {
+            return Err(AppError::InvalidConfig(format!(
+                "Deployment directory already exists: {}; use --force to replace it",
+                args.deployment_dir
+            )));
+        }
+        check_replaceable(deployment_dir)?;
+    }
+
+    let Validation { errors: validation_errors, warnings: validation_warnings, instances, links } = validate_config(&config)?;
+    if !validation_errors.is_empty() {
+        println!("{}", "Validation failed with the following errors:".red().bold());
+        return Err(AppError::InvalidConfig(validation_errors.join("; ")));
+    }
+
+    // The deployment is built in a staging directory next to the deployment directory, and only moved into place once
+    // it is complete, so a failure never leaves a half-built deployment behind
+    let deployment_name = deployment_dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| AppError::InvalidConfig(format!("Invalid deployment directory: {}", args.deployment_dir)))?;
+    let parent_dir = match deployment_dir.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(parent_dir).map_err(AppError::Io)?;
+    let absolute_dir = parent_dir.canonicalize().map_err(AppError::Io)?.join(deployment_name);
+    let staging_dir = parent_dir.join(format!(".{}.staging", deployment_name));
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir).map_err(AppError::Io)?;
+    }
+    fs::create_dir_all(&staging_dir).map_err(AppError::Io)?;
+
+    let mut deployment = Deployment {
+        project: config.name.clone().unwrap_or_else(|| generate_project_id(&absolute_dir)),
+        modules: Vec::new(),
+        spec,
+        dir: staging_dir.clone(),
+    };
+    if let Err(err) = build_deployment(&mut deployment, &config, &instances, &links, &absolute_dir) {
+        let _ = fs::remove_dir_all(&staging_dir);
+        return Err(err);
+    }
+
+    let replaced = deployment_dir.exists();
+    if replaced {
+        fs::remove_dir_all(deployment_dir).map_err(AppError::Io)?;
+    }
+    fs::rename(&staging_dir, deployment_dir).map_err(AppError::Io)?;
+
+    if !validation_warnings.is_empty() {
+        println!("Deployment was created, however the following issues may need to be addressed before it can be started:");
+    }
+    for warning in validation_warnings {
+        println!("{} {}", "WARNING: ".yellow().bold(), warning);
+    }
+
+    if config.auto_ports {
+        println!("\n{}", "Allocated host ports:".yellow().bold());
+        for instance in instances.iter().filter(|instance| !instance.allocated_ports.is_empty()) {
+            let ports: Vec<String> = instance
+                .allocated_ports
+                .iter()
+                .map(|(name, port)| format!("{}={}", name, port))
+                .collect();
+            println!("{:<24} {}", instance.dir_name, ports.join(", "));
+        }
+        println!();
+    }
+
+    if replaced {
+        println!("Replaced the existing deployment; any containers and volumes left from it can be removed with `nibc-forge prune`");
+    }
+    println!("Deployment created successfully!");
+    Ok(())
+}
+
+/// Copy the modules into the deployment's directory and generate the files of the deployment: the .env files, the
+/// rewritten compose files, the Hermes config and the manifest
+fn build_deployment(
+    deployment: &mut Deployment,
+    config: &Config,
+    instances: &[ModuleInstance],
+    links: &[LinkConfig],
+    absolute_dir: &Path,
+) -> Result<(), AppError> {
+    let deployment_dir = deployment.dir.clone();
+    let absolute_dir = absolute_dir.to_string_lossy();
+    let chain_modules: Vec<&ModuleConfig> = config
+        .modules
+        .iter()
+        .filter(|module| module.kind.is_chain())
+        .collect();
+
+    // Copy the module directories to the deployment directory
+    for instance in instances {
+        let module = instance.module;
+        let module_src = module.module_dir.to_string();
+        let module_dst = deployment_dir.join(&instance.dir_name);
+
+        copy_dir_recursively(&module_src, &module_dst)?;
+
+        // Write docker_env variables and HOSTNAME to the module's .env file
+        let env_content = env_file_content(&instance.env);
+        let env_file_path = module_dst.join(".env");
+        fs::write(&env_file_path, env_content).map_err(|err| {
+            AppError::InvalidConfig(format!(
+                "Failed to write .env file to {}: {}",
+                env_file_path.display(),
+                err
+            ))
+        })?;
+
+        // Merge the balances and tokens of a previous deployment into the copied genesis templates
+        if let Some(state_path) = &module.genesis_state {
+            seed_genesis_state(&module_dst.join("genesis"), state_path)?;
+        }
+
+        // Modify docker-compose.yml service names with a suffix if necessary, and label everything it creates with
+        // the deployment so `clean` only ever removes resources of this deployment
+        let compose_file = module_dst.join("docker-compose.yml");
+        if compose_file.exists() {
+            let mut compose_content = fs::read_to_string(&compose_file).map_err(AppError::Io)?;
+            if let Some(suffix) = instance.suffix {
+                compose_content = rename_services(&compose_content, &suffix.to_string())?;
+                fs::write(&compose_file, &compose_content)?;
+            }
+            let labels = labels_override(
+                &compose_content,
+                &[(DEPLOYMENT_LABEL, &deployment.project), (DIR_LABEL, &absolute_dir)],
+            )?;
+            fs::write(module_dst.join(LABELS_COMPOSE_FILE), labels)?;
+        }
+
+        deployment.modules.push(deployed_module(instance, &module_dst, &deployment.project)?);
+    }
+
+    // If a hermes module is present, generate the required chainlist.json and hermes config.toml files based on the other included modules
+    let hermes_module = config.modules.iter().find(|module| module.kind == ModuleType::Hermes);
+    if let Some(hermes) = hermes_module {
+        match Path::new(&hermes.module_dir).file_name() {
+            Some(hermes_dir) => {
+                let json_output_path = deployment_dir
+                    .join(hermes_dir)
+                    .join("chainlist.json");
+                generate_chainlist_json(&chain_modules, links, json_output_path)?;
+                // Report the paths the files will have once the staging directory is moved into place
+                let final_dir = Path::new(absolute_dir.as_ref()).join(hermes_dir);
+                println!("Generated chainlist.json at {}", final_dir.join("chainlist.json").display());
+
+                let config_output_path = deployment_dir
+                    .join(hermes_dir)
+                    .join("config.toml");
+                generate_config_toml(hermes, &chain_modules, links, config_output_path)?;
+                println!("Generated intermediate Hermes config at {}", final_dir.join("config.toml").display());
+            }
+            None => return Err(AppError::Unknown)
+        }
+    }
+
+    deployment.write()
+}
+
+/// Refuse to replace a directory that is not a deployment (unless it is empty), or a deployment that still has running
+/// containers
+fn check_replaceable(deployment_dir: &Path) -> Result<(), AppError> {
+    if !deployment_dir.join(MANIFEST_FILE).exists() {
+        let empty = fs::read_dir(deployment_dir).map_err(AppError::Io)?.next().is_none();
+        if empty {
+            return Ok(());
+        }
+        return Err(AppError::InvalidConfig(format!(
+            "{} is not empty and has no {}, so it is not a nibc-forge deployment; refusing to replace it",
+            deployment_dir.display(),
+            MANIFEST_FILE
+        )));
+    }
+    let deployment = Deployment::load(deployment_dir)?;
+    let ps_output = Command::new("docker")
+        .arg("container")
+        .arg("ls")
+        .arg("--quiet")
+        .arg("--filter")
+        .arg(format!("label=com.docker.compose.project={}", deployment.project))
+        .output()
+        .map_err(AppError::Io)?;
+    if !ps_output.status.success() {
+        return Err(AppError::DockerCommand(format!(
+            "Failed to list the containers of project {}: {}",
+            deployment.project,
+            String::from_utf8_lossy(&ps_output.stderr).trim()
+        )));
+    }
+    if !ps_output.stdout.is_empty() {
         return Err(AppError::InvalidConfig(format!(
-            "Deployment directory already exists: {}",
-            args.deployment_dir
+            "Deployment in {} is running; stop it before replacing it",
+            deployment_dir.display()
         )));
     }
 
+    let snapshots_dir = deployment_dir.join(SNAPSHOTS_DIR);
+    if snapshots_dir.exists() {
+        println!(
+            "{} the snapshots of the existing deployment in {} will be deleted along with it",
+            "WARNING: ".yellow().bold(),
+            snapshots_dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// The manifest record of a module copied to `module_dst`, with the service and container names of its compose file
+fn deployed_module(instance: &ModuleInstance, module_dst: &Path, project: &str) -> Result<DeployedModule, AppError> {
+    let mut deployed = DeployedModule {
+        name: instance.name().to_string(),
+        dir: instance.dir_name.clone(),
+        kind: instance.module.kind,
+        hostname: instance.module.rpc_hostname.clone(),
+        depends_on: instance.module.depends_on.clone(),
+        ports: BTreeMap::new(),
+        services: Vec::new(),
+    };
+
+    let compose_file = module_dst.join("docker-compose.yml");
+    let Ok(content) = fs::read_to_string(&compose_file) else {
+        return Ok(deployed);
+    };
+
+    let env: HashMap<String, String> = instance.env.iter().cloned().collect();
+    for (name, default) in port_variables(&content) {
+        let port = env.get(&name).and_then(|value| value.parse().ok()).or(default);
+        if let Some(port) = port {
+            deployed.ports.insert(name, port);
+        }
+    }
+
+    // Compose names containers `<project>-<service>-1` unless the service sets a container_name
+    for service in compose_resources(&content, &env)?.services {
+        let container = service
+            .container_name
+            .unwrap_or_else(|| format!("{}-{}-1", project, service.name));
+        let ports = service
+            .ports
+            .iter()
+            .map(|(host_port, container_port)| (container_port.to_string(), *host_port))
+            .collect();
+        deployed.services.push(DeployedService { name: service.name, container, ports });
+    }
+
+    Ok(deployed)
+}
+
+/// A module of the config as it is copied into the deployment
+pub struct ModuleInstance<'a> {
+    pub module: &'a ModuleConfig,
+    /// Directory name within the deployment
+    pub dir_name: String,
+    /// Appended to the directory and service names when the same module_dir is listed more than once
+    pub suffix: Option<usize>,
+    /// Variables written to the module's .env file
+    pub env: Vec<(String, String)>,
+    /// Host ports picked for the module's port variables when auto_ports is enabled
+    pub allocated_ports: Vec<(String, u16)>,
+}
+
+impl ModuleInstance<'_> {
+    /// The name the module is recorded under in the deployment manifest
+    pub fn name(&self) -> &str {
+        self.module.name.as_deref().unwrap_or(&self.dir_name)
+    }
+}
+
+fn module_instances(config: &Config) -> Result<Vec<ModuleInstance<'_>>, AppError> {
+    // Keep track of how many times each module_dir has been used, so we can append the count to the directory name
+    let mut module_counts: HashMap<String, usize> = HashMap::new();
+
+    config
+        .modules
+        .iter()
+        .map(|module| {
+            let base_name = Path::new(&module.module_dir)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or(&module.module_dir)
+                .to_string();
+            let count = module_counts.entry(base_name.clone()).or_insert(0);
+            *count += 1;
+
+            let (dir_name, suffix) = if *count > 1 {
+                (format!("{}{}", base_name, count), Some(*count))
+            } else {
+                (base_name, None)
+            };
+            Ok(ModuleInstance { module, dir_name, suffix, env: config.module_env(module)?, allocated_ports: Vec::new() })
+        })
+        .collect()
+}
+
+fn allocate_ports(instances: &mut [ModuleInstance]) -> Result<(), AppError> {
+    // Ports set explicitly in a docker_env are kept and must not be handed out again
+    let mut used_ports: HashSet<u16> = instances
+        .iter()
+        .flat_map(|instance| instance.env.iter())
+        .filter(|(name, _)| name.ends_with("_PORT"))
+        .filter_map(|(_, value)| value.parse().ok())
+        .collect();
+
+    for instance in instances.iter_mut() {
+        let compose_path = Path::new(&instance.module.module_dir).join("docker-compose.yml");
+        let Ok(content) = fs::read_to_string(&compose_path) else {
+            continue;
+        };
+        for (name, default) in port_variables(&content) {
+            if instance.env.iter().any(|(existing, _)| existing == &name) {
+                continue;
+            }
+            let port = allocate_port(default.unwrap_or(10000), &used_ports).ok_or_else(|| {
+                AppError::InvalidConfig(format!("No free host port available for {} of module {}", name, instance.dir_name))
+            })?;
+            used_ports.insert(port);
+            instance.env.push((name.clone(), port.to_string()));
+            instance.allocated_ports.push((name, port));
+        }
+    }
+
+    Ok(())
+}
+
+fn check_resource_conflicts(instances: &[ModuleInstance], errors: &mut Vec<String>, warnings: &mut Vec<String>) {
+    // Map each resource to the module services that use it
+    let mut resource_owners: BTreeMap<(&str, String), Vec<String>> = BTreeMap::new();
+
+    for instance in instances {
+        let compose_path = Path::new(&instance.module.module_dir).join("docker-compose.yml");
+        let Ok(content) = fs::read_to_string(&compose_path) else {
+            continue;
+        };
+        let env: HashMap<String, String> = instance.env.iter().cloned().collect();
+        let resources = match compose_resources(&content, &env) {
+            Ok(resources) => resources,
+            Err(err) => {
+                errors.push(format!("Invalid compose file {}: {}", compose_path.display(), err));
+                continue;
+            }
+        };
+
+        let suffix = instance.suffix.map(|count| count.to_string()).unwrap_or_default();
+        for service in &resources.services {
+            let service_name = format!("{}{}", service.name, suffix);
+            let owner = format!("{}/{}", instance.dir_name, service_name);
+            let mut add = |kind, name: String| resource_owners.entry((kind, name)).or_default().push(owner.clone());
+
+            // Names are checked as they will be after renaming the services of a repeated module (see rename_services)
+            add("service name", service_name.clone());
+            if let Some(hostname) = &service.hostname {
+                let hostname = if hostname == &service.name { service_name.clone() } else { hostname.clone() };
+                add("hostname", hostname);
+            }
+            if let Some(container_name) = &service.container_name {
+                add("container name", format!("{}{}", container_name, suffix));
+            }
+            for (port, _) in &service.ports {
+                add("host port", port.to_string());
+            }
+        }
+        for volume in &resources.volumes {
+            resource_owners.entry(("volume", format!("{}{}", volume, suffix))).or_default().push(instance.dir_name.clone());
+        }
+    }
+
+    for ((kind, name), owners) in &resource_owners {
+        if owners.len() > 1 {
+            errors.push(format!("Conflicting {} {} used by: {}", kind, name, owners.join(", ")));
+        } else if *kind == "host port" {
+            let port: u16 = name.parse().unwrap_or_default();
+            if !port_available(port) {
+                warnings.push(format!("host port {} used by {} is already in use on this machine", port, owners[0]));
+            }
+        }
+    }
+}
+
+/// Parse a spec that has had its `extends` and `include` keys resolved
+pub fn parse_config(spec: toml::Table) -> Result<Config, AppError> {
+    spec.try_into()
+        .map_err(|err| AppError::InvalidConfig(format!("Invalid config file format: {}", err)))
+}
+
+/// The outcome of checking a deployment config, along with the module instances and links that `create` would write
+pub struct Validation<'a> {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    pub instances: Vec<ModuleInstance<'a>>,
+    pub links: Vec<LinkConfig>,
+}
+
+/// Run every check on a deployment config without writing anything to disk
+pub fn validate_config(config: &Config) -> Result<Validation<'_>, AppError> {
     let mut validation_errors = Vec::new();
     let mut validation_warnings = Vec::new();
 
+    if let Some(name) = &config.name {
+        if !valid_project_name(name) {
+            validation_errors.push(format!(
+                "Invalid name {}: must contain only lowercase letters, digits, dashes and underscores, and start with a letter or digit",
+                name
+            ));
+        }
+    }
+
     // Check for exactly one module of type hermes
     let hermes_modules: Vec<&ModuleConfig> = config
         .modules
         .iter()
-        .filter(|module| module._type == "hermes")
+        .filter(|module| module.kind == ModuleType::Hermes)
         .collect();
     if hermes_modules.len() > 1 {
-        return Err(AppError::InvalidConfig(
-            "Config cannot include more than one Hermes module".to_string(),
-        ));
+        validation_errors.push("Config cannot include more than one Hermes module".to_string());
     } else if hermes_modules.is_empty() {
         validation_warnings.push("no Hermes module in config; is this intended? (If so, ignore this warning)".to_string());
     }
@@ -61,16 +457,15 @@ pub fn handle_create(args: crate::CreateArgs) -> Result<(), AppError> {
             ));
         }
 
-        if module._type != "aux" {
-            // If no Hermes template provided, fallback to hermes_templates/{chain-type}.toml
-            let template_src = match &module.hermes_template {
-                Some(path) => Path::new(path).to_path_buf(),
-                None => {
-                    let fallback_msg = format!("hermes_template not specified for module {}; falling back to default value hermes_templates/{}", &module.module_dir, &module._type);
-                    println!("{} {}", format!("WARNING: ").yellow().bold(), fallback_msg);
-                    Path::new(HERMES_TEMPLATE_DIR).join(format!("{}.toml", &module._type))
-                }
-            };
+        // If no Hermes template provided, fallback to hermes_templates/{type}.toml
+        if let Some(template_src) = module.hermes_template() {
+            if module.hermes_template.is_none() {
+                validation_warnings.push(format!(
+                    "hermes_template not specified for module {}; falling back to default value {}",
+                    &module.module_dir,
+                    template_src.display()
+                ));
+            }
             if !template_src.exists() {
                 validation_errors.push(format!(
                     "Hermes template {} not found for module {}",
@@ -79,141 +474,77 @@ pub fn handle_create(args: crate::CreateArgs) -> Result<(), AppError> {
                 ));
             }
         }
+
+        // A genesis state file can only seed Namada chains and must be in the format written by `dump-db`
+        if let Some(state_path) = &module.genesis_state {
+            if module.kind != ModuleType::Namada {
+                validation_errors.push(format!(
+                    "genesis_state is only supported for namada modules: {}",
+                    module.module_dir
+                ));
+            } else if let Err(err) = read_ledger_state(state_path) {
+                validation_errors.push(err.to_string());
+            }
+        }
     }
 
-    // TODO: check docker compose files for conflicting host ports, volume names, service names, or docker hostnames
-    // // Check for conflicts in docker-compose.yml
-    // let mut resource_map: HashMap<String, HashSet<String>> = HashMap::new();
-    // for module in &config.modules {
-    //     let compose_path = Path::new(&module.module_dir).join("docker-compose.yml");
-    //     if compose_path.exists() {
-    //         let content = fs::read_to_string(&compose_path).unwrap_or_default();
-    //         for line in content.lines() {
-    //             if let Some(resource) = extract_docker_resource(line) {
-    //                 resource_map
-    //                     .entry(resource.clone())
-    //                     .or_default()
-    //                     .insert(module.module_dir.clone());
-    //             }
-    //         }
-    //     }
-    // }
-    // for (resource, dirs) in resource_map {
-    //     if dirs.len() > 1 {
-    //         validation_warnings.push(format!(
-    //             "Docker Resource conflict for {} between: {}",
-    //             resource,
-    //             dirs.into_iter().collect::<Vec<_>>().join(", ")
-    //         ));
-    //     }
-    // }
+    // Check the docker compose files for conflicting host ports, hostnames, container names, service names or volumes
+    let mut instances = module_instances(config)?;
+    if config.auto_ports {
+        allocate_ports(&mut instances)?;
+    }
+    check_resource_conflicts(&instances, &mut validation_errors, &mut validation_warnings);
+    validation_errors.extend(validate_dependencies(&instances));
 
     // Filter chain modules and validate relayer_key presence
     let chain_modules: Vec<&ModuleConfig> = config
         .modules
         .iter()
-        .filter(|module| module._type != "aux" && module._type != "hermes")
+        .filter(|module| module.kind.is_chain())
         .collect();
     for module in &chain_modules {
-        if module.relayer_key.is_none() || module.relayer_key == Some("".to_string()) {
-            validation_errors.push(format!(
+        match module.relayer_key.as_deref() {
+            None | Some("") => validation_errors.push(format!(
                 "Relayer key not provided for {}",
                 module.module_dir
-            ));
-        }
-    }
-
-    if !validation_errors.is_empty() {
-        println!("{}", format!("Validation failed with the following errors:").red().bold());
-        return Err(AppError::InvalidConfig(format!(
-            "{}",
-            validation_errors.join("; ")
-        )));
-    }
-
-    // Create the deployment directory
-    fs::create_dir_all(deployment_dir).map_err(AppError::Io)?;
-
-    // HashMap to keep track of how many times each module_dir has been copied, so we can append the count to the directory name
-    let mut module_counts: HashMap<String, usize> = HashMap::new();
-
-    // Copy the module directories to the deployment directory
-    for module in &config.modules {
-        let module_src = format!("{}", module.module_dir);
-        let base_name = Path::new(&module_src).file_name().unwrap().to_str().unwrap();
-
-        // Determine a unique destination directory
-        let mut module_dst = deployment_dir.join(&base_name);
-        let count = module_counts.entry(base_name.to_string()).or_insert(1);
-        
-        if *count > 1 {
-            module_dst = deployment_dir.join(format!("{}{}", base_name, count));
-        }
-
-        copy_dir_recursively(&module_src, &module_dst)?;
-
-        // Write docker_env variables to the module's .env file
-        let mut env_content = String::new();
-        if let Some(variable_list) = &module.docker_env {
-            env_content.push_str(&variable_list.replace(',', "\n"));
-            env_content.push('\n');
-        }
-        // Add HOSTNAME to .env
-        if let Some(hostname) = &module.rpc_hostname {
-            env_content.push_str(&format!("HOSTNAME={}\n", hostname));
-        }
-        let env_file_path = module_dst.join(".env");
-        fs::write(&env_file_path, env_content).map_err(|err| {
-            AppError::InvalidConfig(format!(
-                "Failed to write .env file to {}: {}",
-                env_file_path.display(),
-                err
-            ))
-        })?;
-
-        // Modify docker-compose.yml service names with a suffix if necessary
-        if *count > 1 {
-            let compose_file = module_dst.join("docker-compose.yml");
-            if compose_file.exists() {
-                let compose_content = fs::read_to_string(&compose_file).map_err(AppError::Io)?;
-                let updated_content = modify_service_names(&compose_content, &count.to_string());
-                fs::write(&compose_file, updated_content?)?;
+            )),
+            Some(key) => {
+                if let Some(Err(err)) = module.kind.chain_type().map(|chain| chain.validate_relayer_key(key)) {
+                    validation_errors.push(format!(
+                        "Invalid relayer key for {}: {}",
+                        module.module_dir, err
+                    ));
+                }
             }
         }
-        *count += 1;
     }
 
-    // If a hermes module is present, generate the required chainlist.json and hermes config.toml files based on the other included modules
-    let hermes_module = config.modules.iter().find(|module| module._type == "hermes");
-    if let Some(hermes) = hermes_module {
-        match Path::new(&hermes.module_dir).file_name() {
-            Some(hermes_dir) => {
-                let json_output_path = Path::new(deployment_dir)
-                    .join(&hermes_dir)
-                    .join("chainlist.json");
-                generate_chainlist_json(&chain_modules, json_output_path)?;
-
-                let config_output_path = Path::new(deployment_dir)
-                    .join(&hermes_dir)
-                    .join("config.toml");
-                generate_config_toml(&hermes, &chain_modules, config_output_path)?;
-            }
-            None => return Err(AppError::Unknown)
+    // Validate the IBC links between the chains
+    let chain_hostnames: Vec<&str> = chain_modules
+        .iter()
+        .filter_map(|module| module.rpc_hostname.as_deref())
+        .collect();
+    validation_errors.extend(validate_links(config, &chain_hostnames));
+    let links = resolve_links(config, &chain_hostnames);
+    if !links.is_empty() {
+        let topology_set = instances
+            .iter()
+            .filter(|instance| instance.module.kind == ModuleType::Hermes)
+            .any(|instance| instance.env.iter().any(|(name, _)| name == "TOPOLOGY"));
+        if topology_set {
+            validation_warnings.push("TOPOLOGY is ignored by Hermes when hub or links are specified in the config".to_string());
         }
     }
 
-    if !validation_warnings.is_empty() {
-        println!("Deployment will be created however the following issues may need to be addressed before it can be started:");
-    }
-    for warning in validation_warnings {
-        println!("{} {}", format!("WARNING: ").yellow().bold(), warning);
-    }
-
-    println!("Deployment created successfully!");
-    Ok(())
+    Ok(Validation {
+        errors: validation_errors,
+        warnings: validation_warnings,
+        instances,
+        links,
+    })
 }
 
-fn copy_dir_recursively(src: &str, dst: &Path) -> Result<(), AppError> {
+pub fn copy_dir_recursively(src: &str, dst: &Path) -> Result<(), AppError> {
     // Ensure the destination directory exists
     fs::create_dir_all(dst).map_err(AppError::Io)?;
 
@@ -243,52 +574,210 @@ fn copy_dir_recursively(src: &str, dst: &Path) -> Result<(), AppError> {
     Ok(())
 }
 
-// fn extract_docker_resource(line: &str) -> Option<String> {
-//     // Simplified placeholder
-//     if line.contains("hostname:") {
-//         Some(line.trim().to_string())
-//     } else {
-//         None
-//     }
-// }
-
-#[derive(Debug, Deserialize, Serialize)]
-struct DockerCompose {
-    services: HashMap<String, Value>, // The services section
-    // You can include other sections like `version`, `networks`, etc., as needed
+// Modules are referred to by their name, or by their directory in the deployment if they have none
+fn validate_dependencies(instances: &[ModuleInstance]) -> Vec<String> {
+    let mut errors = Vec::new();
+    let names: Vec<&str> = instances.iter().map(ModuleInstance::name).collect();
+
+    let mut dependencies: HashMap<&str, Vec<&str>> = HashMap::new();
+    for instance in instances {
+        let Some(depends_on) = &instance.module.depends_on else {
+            continue;
+        };
+        for dependency in depends_on {
+            if !names.contains(&dependency.as_str()) {
+                errors.push(format!("Module {} depends on unknown module {}", instance.name(), dependency));
+            } else if dependency == instance.name() {
+                errors.push(format!("Module {} depends on itself", instance.name()));
+            } else {
+                dependencies.entry(instance.name()).or_default().push(dependency);
+            }
+        }
+    }
+
+    // Follow the dependencies of every module; reaching the module again means they form a cycle
+    for name in &names {
+        let mut stack: Vec<&str> = dependencies.get(name).cloned().unwrap_or_default();
+        let mut seen: HashSet<&str> = HashSet::new();
+        while let Some(dependency) = stack.pop() {
+            if dependency == *name {
+                errors.push(format!("Module {} has a circular dependency", name));
+                break;
+            }
+            if seen.insert(dependency) {
+                stack.extend(dependencies.get(dependency).cloned().unwrap_or_default());
+            }
+        }
+    }
+
+    errors
 }
 
-fn modify_service_names(compose_content: &str, suffix: &str) -> Result<String, AppError> {
-    // Parse the YAML content into a DockerCompose struct
-    let mut docker_compose: DockerCompose = serde_yaml::from_str(compose_content)
-        .map_err(|err| AppError::InvalidConfig(format!("Failed to deserialize YAML: {}", err)))?;
-
-    // Iterate through the services and append the suffix to the service names
-    let services = docker_compose.services.clone();
-    for (service_name, _) in services {
-        if let Some(_service) = docker_compose.services.get_mut(&service_name) {
-            let new_name = format!("{}{}", service_name, suffix);
-            if let Some(service) = docker_compose.services.remove(&service_name) {
-                docker_compose.services.insert(new_name, service);
+fn validate_links(config: &Config, chain_hostnames: &[&str]) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if let Some(hub) = &config.hub {
+        if !config.links.is_empty() {
+            errors.push("hub and links cannot both be specified; list the links of the hub chain explicitly instead".to_string());
+        }
+        if !chain_hostnames.contains(&hub.as_str()) {
+            errors.push(format!("Hub {} does not match the rpc_hostname of any chain module", hub));
+        }
+    }
+
+    let mut seen_links = HashSet::new();
+    for link in &config.links {
+        for chain in [&link.a_chain, &link.b_chain] {
+            if !chain_hostnames.contains(&chain.as_str()) {
+                errors.push(format!("Link chain {} does not match the rpc_hostname of any chain module", chain));
             }
         }
+        if link.a_chain == link.b_chain {
+            errors.push(format!("Link cannot connect chain {} to itself", link.a_chain));
+        }
+        if link.a_port.is_empty() || link.b_port.is_empty() {
+            errors.push(format!("Link between {} and {} has an empty port id", link.a_chain, link.b_chain));
+        }
+        if link.ordering != "ordered" && link.ordering != "unordered" {
+            errors.push(format!(
+                "Invalid ordering '{}' for link between {} and {}; must be one of 'ordered' or 'unordered'",
+                link.ordering, link.a_chain, link.b_chain
+            ));
+        }
+
+        // The same channel is described by both (a, b) and (b, a)
+        let a_end = (&link.a_chain, &link.a_port);
+        let b_end = (&link.b_chain, &link.b_port);
+        let key = if a_end <= b_end { (a_end, b_end) } else { (b_end, a_end) };
+        if !seen_links.insert(key) {
+            errors.push(format!(
+                "Duplicate link between {}:{} and {}:{}",
+                link.a_chain, link.a_port, link.b_chain, link.b_port
+            ));
+        }
     }
 
-    // Serialize the updated DockerCompose back into YAML
-    let updated_yaml = to_string(&docker_compose).map_err(|err| AppError::InvalidConfig(format!("Failed to serialize YAML: {}", err)))?;
-    
-    Ok(updated_yaml)
+    errors
 }
 
-fn generate_chainlist_json(modules: &Vec<&ModuleConfig>, output_path: PathBuf) -> Result<(), AppError> {
+// The links Hermes should create; either those listed in the config or generated from the named hub.
+// An empty list leaves the topology to the TOPOLOGY variable of the Hermes module
+fn resolve_links(config: &Config, chain_hostnames: &[&str]) -> Vec<LinkConfig> {
+    match &config.hub {
+        Some(hub) => chain_hostnames
+            .iter()
+            .filter(|hostname| *hostname != hub)
+            .map(|hostname| LinkConfig {
+                a_chain: hub.clone(),
+                b_chain: hostname.to_string(),
+                a_port: "transfer".to_string(),
+                b_port: "transfer".to_string(),
+                ordering: "unordered".to_string(),
+                version: None,
+            })
+            .collect(),
+        None => config.links.clone(),
+    }
+}
+
+fn read_ledger_state(state_path: &str) -> Result<LedgerState, AppError> {
+    let state_content = fs::read_to_string(state_path).map_err(|err| {
+        AppError::InvalidConfig(format!("Failed to read genesis state file {}: {}", state_path, err))
+    })?;
+    toml::from_str(&state_content).map_err(|err| {
+        AppError::InvalidConfig(format!("Invalid genesis state file {}: {}", state_path, err))
+    })
+}
+
+fn seed_genesis_state(genesis_dir: &Path, state_path: &str) -> Result<(), AppError> {
+    let state = read_ledger_state(state_path)?;
+
+    // Add any tokens not already defined by the template
+    let tokens_path = genesis_dir.join("tokens.toml");
+    let mut tokens_doc = read_toml_document(&tokens_path)?;
+    if let Some(toml::Value::Table(tokens)) = state.tokens.get("token") {
+        let token_table = tokens_doc.entry("token").or_insert(table());
+        for (alias, token_config) in tokens {
+            if token_table.get(alias).is_none() {
+                let token_doc: DocumentMut = toml::to_string(token_config)
+                    .map_err(|err| AppError::InvalidConfig(format!("Failed to serialize token {}: {}", alias, err)))?
+                    .parse()
+                    .map_err(|err| AppError::InvalidConfig(format!("Failed to parse token {}: {}", alias, err)))?;
+                token_table[alias] = Item::Table(token_doc.as_table().clone());
+            }
+        }
+    }
+    let known_tokens: Vec<String> = tokens_doc["token"]
+        .as_table_like()
+        .map(|tokens| tokens.iter().map(|(alias, _)| alias.to_string()).collect())
+        .unwrap_or_default();
+    fs::write(&tokens_path, tokens_doc.to_string()).map_err(AppError::Io)?;
+
+    // Merge the balances; values from the state file take precedence over the template
+    let balances_path = genesis_dir.join("balances.toml");
+    let mut balances_doc = read_toml_document(&balances_path)?;
+    let balance_table = balances_doc.entry("token").or_insert(table());
+    for (token, holders) in &state.balances.token {
+        if !known_tokens.contains(token) {
+            println!("{} skipping genesis balances for unknown token {}", "WARNING: ".yellow().bold(), token);
+            continue;
+        }
+        let token_balances = balance_table[token.as_str()].or_insert(table());
+        for (address, amount) in holders {
+            token_balances[address.as_str()] = value(amount.as_str());
+        }
+    }
+
+    // The Namada init-chain.sh script appends its own balances to the end of the file, so the NAM table must come last
+    if let Some(balance_tables) = balance_table.as_table_mut() {
+        balance_tables.set_implicit(true);
+        let mut position = 1;
+        let mut aliases: Vec<String> = balance_tables.iter().map(|(alias, _)| alias.to_string()).collect();
+        aliases.sort_by_key(|alias| alias == "nam");
+        for alias in aliases {
+            if let Some(token_balances) = balance_tables[alias.as_str()].as_table_mut() {
+                token_balances.set_position(position);
+                position += 1;
+            }
+        }
+    }
+    let mut balances_content = balances_doc.to_string();
+    if !balances_content.ends_with('\n') {
+        balances_content.push('\n');
+    }
+    fs::write(&balances_path, balances_content).map_err(AppError::Io)?;
+
+    if !state.ibc_balances.is_empty() || !state.bonds.is_empty() {
+        println!(
+            "{} IBC token balances and bonds from {} cannot be recreated in a genesis and were skipped",
+            "WARNING: ".yellow().bold(),
+            state_path
+        );
+    }
+
+    println!("Seeded genesis balances and tokens from {}", state_path);
+
+    Ok(())
+}
+
+fn read_toml_document(path: &Path) -> Result<DocumentMut, AppError> {
+    fs::read_to_string(path)
+        .map_err(|err| AppError::InvalidConfig(format!("Failed to read {}: {}", path.display(), err)))?
+        .parse()
+        .map_err(|err| AppError::InvalidConfig(format!("Failed to parse {}: {}", path.display(), err)))
+}
+
+fn generate_chainlist_json(modules: &Vec<&ModuleConfig>, links: &[LinkConfig], output_path: PathBuf) -> Result<(), AppError> {
     let mut chain_json = Vec::new();
 
     for module in modules {
-        if let (Some(hostname), Some(relayer_key)) = (&module.rpc_hostname, &module.relayer_key) {
+        if let (Some(hostname), Some(relayer_key), Some(chain)) = (&module.rpc_hostname, &module.relayer_key, module.kind.chain_type()) {
             chain_json.push(json!({
                 "hostname": hostname,
                 "key": relayer_key,
-                "type": module._type,
+                "type": chain.name(),
+                "denom_query": chain.fee_denom_query(),
+                "key_import": chain.relayer_key_import(),
             }));
         } else {
             return Err(AppError::InvalidConfig(format!(
@@ -298,8 +787,25 @@ fn generate_chainlist_json(modules: &Vec<&ModuleConfig>, output_path: PathBuf) -
         }
     }
 
-    // Write the chain_json to the file
-    let json_content = serde_json::to_string_pretty(&chain_json).map_err(|err| {
+    let links_json: Vec<_> = links
+        .iter()
+        .map(|link| {
+            json!({
+                "a_chain": link.a_chain,
+                "b_chain": link.b_chain,
+                "a_port": link.a_port,
+                "b_port": link.b_port,
+                "ordering": link.ordering,
+                "version": link.version,
+            })
+        })
+        .collect();
+
+    // Write the chains and links to the file
+    let json_content = serde_json::to_string_pretty(&json!({
+        "chains": chain_json,
+        "links": links_json,
+    })).map_err(|err| {
         AppError::InvalidConfig(format!("Failed to serialize JSON: {}", err))
     })?;
 
@@ -310,7 +816,6 @@ fn generate_chainlist_json(modules: &Vec<&ModuleConfig>, output_path: PathBuf) -
         ))
     })?;
 
-    println!("Generated chainlist.json at {}", output_path.display());
 
     Ok(())
 }
@@ -318,10 +823,11 @@ fn generate_chainlist_json(modules: &Vec<&ModuleConfig>, output_path: PathBuf) -
 fn generate_config_toml(
     hermes_module: &ModuleConfig,
     chain_modules: &Vec<&ModuleConfig>,
+    links: &[LinkConfig],
     output_path: PathBuf,
 ) -> Result<(), AppError> {
     // Load the base Hermes template
-    let mut output_toml_content = fs::read_to_string(&hermes_module.hermes_template.as_ref().ok_or_else(|| {
+    let mut output_toml_content = fs::read_to_string(hermes_module.hermes_template().ok_or_else(|| {
         AppError::InvalidConfig(format!(
             "Hermes module {} missing template",
             &hermes_module.module_dir
@@ -330,7 +836,7 @@ fn generate_config_toml(
 
     // Process each chain module and append its modified template
     for (index, module) in chain_modules.iter().enumerate() {
-        let chain_template_path = module.hermes_template.as_ref().ok_or_else(|| {
+        let chain_template_path = module.hermes_template().ok_or_else(|| {
             AppError::InvalidConfig(format!(
                 "Module {} missing Hermes template",
                 &module.module_dir
@@ -355,15 +861,42 @@ fn generate_config_toml(
         let denom_placeholder = format!("DENOM_{index}");
         let chain_id_placeholder = format!("CHAIN_{index}");
 
+        // Ports used by this chain's links, which must be allowed by the packet filter for Hermes to relay them
+        let hostname = module.rpc_hostname.as_deref().unwrap_or_default();
+        let mut link_ports: Vec<&str> = Vec::new();
+        for link in links {
+            for (chain, port) in [(&link.a_chain, &link.a_port), (&link.b_chain, &link.b_port)] {
+                if chain == hostname && !link_ports.contains(&port.as_str()) {
+                    link_ports.push(port);
+                }
+            }
+        }
+
         // Access the `[[chains]]` array (we iterate here but this array is expected to contain only a single item)
         if let Item::ArrayOfTables(chains) = &mut chain_doc["chains"] {
             for chain in chains.iter_mut() {
+                if chain["packet_filter"]["policy"].as_str() == Some("allow") {
+                    if let Some(filters) = chain["packet_filter"]["list"].as_array_mut() {
+                        for port in &link_ports {
+                            let allowed = filters.iter().any(|filter| {
+                                filter.as_array().and_then(|f| f.get(0)).and_then(|p| p.as_str()) == Some(port)
+                            });
+                            if !allowed {
+                                filters.push(Array::from_iter([*port, "channel-*"]));
+                            }
+                        }
+                    }
+                }
+
                 chain["id"] = value(chain_id_placeholder.clone());
                 chain["rpc_addr"] = value(format!("http://{}:26657", host_placeholder));
                 chain["grpc_addr"] = value(format!("http://{}:9090", host_placeholder));
                 chain["event_source"]["url"] = value(format!("ws://{}:26657/websocket", host_placeholder));
                 chain["gas_price"]["denom"] = value(denom_placeholder.clone());
                 chain["key_name"] = value(key_placeholder.clone());
+                if let Some(chain_type) = module.kind.chain_type() {
+                    chain["account_prefix"] = value(chain_type.account_prefix());
+                }
             }
         }
 
@@ -378,7 +911,6 @@ fn generate_config_toml(
         ))
     })?;
 
-    println!("Generated intermediate Hermes config at {}", output_path.display());
 
     Ok(())
 }
\ No newline at end of file
@@ -1,8 +1,227 @@
+use crate::config::ModuleType;
+use crate::deployment::Deployment;
 use crate::error::AppError;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// Location of the genesis templates used by the Namada module's init-chain.sh script inside the node container
+const NAMADA_GENESIS_DIR: &str = "/root/ammended-genesis";
+const NAMADA_NODE: &str = "http://127.0.0.1:26657";
+
+/// Snapshot of the Namada ledger state. The `tokens` and `balances` tables use the same layout as the
+/// `tokens.toml` and `balances.toml` genesis templates so they can be fed back into a new genesis.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct LedgerState {
+    /// Wallet aliases known to the node, mapped to their addresses
+    #[serde(default)]
+    pub accounts: BTreeMap<String, String>,
+    /// Same layout as tokens.toml: `[token.<alias>]` tables. These are the token configs of the genesis, not of the
+    /// ledger, so tokens added after genesis are missing
+    #[serde(default)]
+    pub tokens: toml::Table,
+    /// Same layout as balances.toml: `[token.<alias>]` tables of `address = "amount"`
+    #[serde(default)]
+    pub balances: GenesisBalances,
+    /// Balances of IBC-minted tokens keyed by denom trace; these cannot be recreated in a genesis
+    #[serde(default)]
+    pub ibc_balances: BTreeMap<String, BTreeMap<String, String>>,
+    #[serde(default)]
+    pub bonds: Vec<Bond>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct GenesisBalances {
+    #[serde(default)]
+    pub token: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Bond {
+    pub source: String,
+    pub validator: String,
+    pub amount: String,
+}
 
 pub fn handle_dump_db(args: crate::DumpDbArgs) -> Result<(), AppError> {
-    println!("Dumping database to file: {}", args.output_file);
-    println!("Not implemented yet");
-    // Add logic here
+    println!(
+        "Dumping Namada ledger state for deployment in directory: {} to file: {}",
+        args.deployment_dir, args.output_file
+    );
+
+    let deployment = Deployment::load(Path::new(&args.deployment_dir))?;
+    let module = deployment
+        .module(&args.module)
+        .filter(|module| module.kind == ModuleType::Namada)
+        .ok_or_else(|| {
+            AppError::InvalidConfig(format!("Namada module {} not found in deployment", args.module))
+        })?;
+    let container = module_container(&deployment.compose_file(module), &deployment.project)?;
+
+    let mut state = LedgerState::default();
+
+    // Token configs are taken from the genesis templates the chain was initialized with
+    let tokens_content = namada_exec(&container, &["cat", &format!("{}/tokens.toml", NAMADA_GENESIS_DIR)])?;
+    let tokens_doc: toml::Table = toml::from_str(&tokens_content)
+        .map_err(|err| AppError::InvalidConfig(format!("Failed to parse genesis tokens.toml: {}", err)))?;
+    state.tokens = tokens_doc;
+    let token_aliases: BTreeSet<String> = match state.tokens.get("token") {
+        Some(toml::Value::Table(tokens)) => tokens.keys().cloned().collect(),
+        _ => BTreeSet::new(),
+    };
+
+    // Collect the owners to query: every address in the node's wallet plus every genesis balance holder
+    let wallet_output = namada_exec(&container, &["namadaw", "list", "--addr"])?;
+    let mut owners = BTreeSet::new();
+    for (alias, address) in parse_wallet_addresses(&wallet_output) {
+        if token_aliases.contains(&alias) {
+            continue;
+        }
+        owners.insert(address.clone());
+        state.accounts.insert(alias, address);
+    }
+
+    let balances_content = namada_exec(&container, &["cat", &format!("{}/balances.toml", NAMADA_GENESIS_DIR)])?;
+    let genesis_balances: GenesisBalances = toml::from_str(&balances_content)
+        .map_err(|err| AppError::InvalidConfig(format!("Failed to parse genesis balances.toml: {}", err)))?;
+    for holders in genesis_balances.token.values() {
+        owners.extend(holders.keys().cloned());
+    }
+
+    for owner in &owners {
+        println!("Querying account {}", owner);
+
+        let balance_output = namada_exec(&container, &["namadac", "balance", "--owner", owner, "--node", NAMADA_NODE])?;
+        for (token, amount) in parse_balances(&balance_output) {
+            // IBC-minted tokens are reported by their denom trace, eg. transfer/channel-0/uatom
+            let balances = if token.contains('/') {
+                state.ibc_balances.entry(token).or_default()
+            } else {
+                state.balances.token.entry(token).or_default()
+            };
+            balances.insert(owner.clone(), amount);
+        }
+
+        let bonds_output = namada_exec(&container, &["namadac", "bonds", "--owner", owner, "--node", NAMADA_NODE])?;
+        state.bonds.extend(parse_bonds(&bonds_output));
+    }
+
+    let output = toml::to_string_pretty(&state)
+        .map_err(|err| AppError::InvalidConfig(format!("Failed to serialize ledger state: {}", err)))?;
+    // The header keeps readers of the file from taking the tokens for those of the ledger
+    let output = format!(
+        "# Namada ledger state dumped by nibc-forge dump-db. The tokens table holds the token configs of the chain's\n\
+         # genesis; tokens added after genesis are not included\n\n{}",
+        output
+    );
+    fs::write(&args.output_file, output).map_err(|err| {
+        AppError::InvalidConfig(format!(
+            "Failed to write ledger state to {}: {}",
+            args.output_file, err
+        ))
+    })?;
+
+    println!(
+        "Dumped {} accounts, {} bonds and balances for {} tokens ({} IBC) to {}",
+        owners.len(),
+        state.bonds.len(),
+        state.balances.token.len(),
+        state.ibc_balances.len(),
+        args.output_file
+    );
+    println!("The token configs are those of the chain's genesis; tokens added after genesis are not included");
+
     Ok(())
 }
+
+fn module_container(compose_file: &Path, project_name: &str) -> Result<String, AppError> {
+    let ps_output = Command::new("docker")
+        .arg("compose")
+        .arg("-f")
+        .arg(compose_file.to_str().unwrap())
+        .arg("--project-name")
+        .arg(project_name)
+        .arg("ps")
+        .arg("-q")
+        .output()
+        .map_err(AppError::Io)?;
+
+    String::from_utf8_lossy(&ps_output.stdout)
+        .lines()
+        .next()
+        .map(String::from)
+        .ok_or_else(|| {
+            AppError::DockerCommand(format!(
+                "No running container found for {}; is the deployment started?",
+                compose_file.display()
+            ))
+        })
+}
+
+fn namada_exec(container: &str, command: &[&str]) -> Result<String, AppError> {
+    let output = Command::new("docker")
+        .arg("exec")
+        .arg(container)
+        .args(command)
+        .output()
+        .map_err(AppError::Io)?;
+
+    if !output.status.success() {
+        return Err(AppError::DockerCommand(format!(
+            "`{}` failed in container {}: {}",
+            command.join(" "),
+            container,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Wallet entries are listed as `"alias": Implicit: tnam1...`
+fn parse_wallet_addresses(output: &str) -> Vec<(String, String)> {
+    let re = Regex::new(r#""([^"]+)":\s*(?:\w+:\s*)?(tnam1[0-9a-z]+)"#).unwrap();
+    re.captures_iter(output)
+        .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+        .collect()
+}
+
+// Balances are listed one token per line as `token: amount`
+fn parse_balances(output: &str) -> Vec<(String, String)> {
+    let re = Regex::new(r"^\s*([^\s:]+):\s+([0-9][0-9.]*)\s*$").unwrap();
+    output
+        .lines()
+        .filter_map(|line| re.captures(line))
+        .filter(|caps| !caps[2].trim_matches(|c| c == '0' || c == '.').is_empty())
+        .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+        .collect()
+}
+
+// Bonds are listed as a `Self-bonds from <validator>:` or `Delegations from <source> to <validator>:` header,
+// followed by the individual bonds and a line with the total active amount
+fn parse_bonds(output: &str) -> Vec<Bond> {
+    let header_re = Regex::new(r"(?:Self-bonds|Delegations) from (tnam1[0-9a-z]+)(?: to (tnam1[0-9a-z]+))?:").unwrap();
+    let total_re = Regex::new(r"Active \(slashable\) (?:self-)?bonds total:\s*([0-9.]+)").unwrap();
+
+    let mut bonds = Vec::new();
+    let mut current: Option<(String, String)> = None;
+    for line in output.lines() {
+        if let Some(caps) = header_re.captures(line) {
+            let source = caps[1].to_string();
+            let validator = caps.get(2).map_or(source.clone(), |m| m.as_str().to_string());
+            current = Some((source, validator));
+        } else if let Some(caps) = total_re.captures(line) {
+            if let Some((source, validator)) = current.take() {
+                bonds.push(Bond {
+                    source,
+                    validator,
+                    amount: caps[1].to_string(),
+                });
+            }
+        }
+    }
+    bonds
+}
@@ -0,0 +1,288 @@
+use crate::commands::ibc_channels::{channel_results, ChannelResult};
+use crate::commands::status::{chain_status, RPC_PORT};
+use crate::compose::env_file_content;
+use crate::config::ModuleType;
+use crate::deployment::{DeployedModule, Deployment};
+use crate::error::AppError;
+use crate::EndpointsFormat;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Container ports of the REST API and gRPC server of the Cosmos SDK chains
+const REST_PORT: u16 = 1317;
+const GRPC_PORT: u16 = 9090;
+
+/// What a frontend needs to connect to one module of a deployment
+#[derive(Serialize)]
+pub struct ModuleEndpoints {
+    pub module: String,
+    #[serde(rename = "type")]
+    pub kind: ModuleType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub native_denom: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_denom: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grpc: Option<String>,
+    /// URLs of the published ports of modules that are not chains, such as indexers, keyed by service (and container
+    /// port, for services publishing more than one)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub services: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<ChannelEndpoint>,
+}
+
+/// An IBC channel from a chain to another chain of the deployment
+#[derive(Serialize)]
+pub struct ChannelEndpoint {
+    pub counterparty: String,
+    pub counterparty_chain_id: String,
+    pub port: String,
+    pub channel: String,
+    pub counterparty_channel: String,
+}
+
+pub fn handle_endpoints(args: crate::EndpointsArgs) -> Result<(), AppError> {
+    let deployment = Deployment::load(Path::new(&args.deployment_dir))?;
+    let endpoints = module_endpoints(&deployment);
+
+    let output = match args.format {
+        EndpointsFormat::Json => serde_json::to_string_pretty(&endpoints)
+            .map_err(|err| AppError::InvalidConfig(format!("Failed to serialize endpoints: {}", err)))?,
+        EndpointsFormat::Yaml => serde_yaml::to_string(&endpoints)
+            .map_err(|err| AppError::InvalidConfig(format!("Failed to serialize endpoints: {}", err)))?,
+        EndpointsFormat::Env => env_file_content(&env_variables(&endpoints, &args.env_prefix)),
+    };
+    println!("{}", output.trim_end());
+    Ok(())
+}
+
+/// The endpoints of every module of a deployment. Chain ids, fee denoms and channels are read from the running chains
+/// and Hermes, and left out while they are not available
+pub fn module_endpoints(deployment: &Deployment) -> Vec<ModuleEndpoints> {
+    let hermes_container = deployment.hermes_container().ok();
+    let hermes_chains = hermes_container.map(hermes_chains).unwrap_or_default();
+    let channels: Vec<ChannelResult> = hermes_container
+        .and_then(|container| channel_results(container).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|result| result.status == "success")
+        .collect();
+
+    let mut endpoints: Vec<ModuleEndpoints> = deployment
+        .modules
+        .iter()
+        .map(|module| {
+            let url = |container_port: u16| {
+                module.published_port(container_port).map(|port| format!("http://127.0.0.1:{}", port))
+            };
+            let mut endpoints = ModuleEndpoints {
+                module: module.name.clone(),
+                kind: module.kind,
+                chain_id: None,
+                native_denom: None,
+                fee_denom: None,
+                rpc: None,
+                rest: None,
+                grpc: None,
+                services: BTreeMap::new(),
+                channels: Vec::new(),
+            };
+
+            if !module.kind.is_chain() {
+                for service in &module.services {
+                    for (container_port, host_port) in &service.ports {
+                        let key = match service.ports.len() {
+                            1 => service.name.clone(),
+                            _ => format!("{}_{}", service.name, container_port),
+                        };
+                        endpoints.services.insert(key, format!("http://127.0.0.1:{}", host_port));
+                    }
+                }
+                return endpoints;
+            }
+
+            let hermes_chain = module
+                .hostname
+                .as_deref()
+                .and_then(|hostname| hermes_chains.iter().find(|chain| chain.0 == hostname));
+            endpoints.chain_id = chain_status(module).chain_id.or(hermes_chain.map(|chain| chain.1.clone()));
+            endpoints.fee_denom = hermes_chain.map(|chain| chain.2.clone());
+            // Namada has no DENOM variable; its fees are paid in its native token
+            endpoints.native_denom = module_variable(deployment, module, "DENOM").or(endpoints.fee_denom.clone());
+            endpoints.rpc = url(RPC_PORT);
+            endpoints.rest = url(REST_PORT);
+            endpoints.grpc = url(GRPC_PORT);
+            endpoints
+        })
+        .collect();
+
+    // Each channel is listed on both of its chains, with the other chain as the counterparty
+    let chains: Vec<(&DeployedModule, Option<String>)> = deployment
+        .modules
+        .iter()
+        .zip(&endpoints)
+        .filter(|(module, _)| module.kind.is_chain())
+        .map(|(module, endpoints)| (module, endpoints.chain_id.clone()))
+        .collect();
+    let chain_module = |hostname: Option<&str>, chain_id: &str| {
+        chains
+            .iter()
+            .find(|(module, id)| match hostname {
+                Some(hostname) => module.hostname.as_deref() == Some(hostname),
+                None => id.as_deref() == Some(chain_id),
+            })
+            .map(|(module, _)| module.name.clone())
+    };
+    for result in &channels {
+        let sides = channel_sides(result);
+        for (side, other) in [(&sides[0], &sides[1]), (&sides[1], &sides[0])] {
+            let (Some(module), Some(counterparty)) = (
+                chain_module(side.hostname, side.chain_id),
+                chain_module(other.hostname, other.chain_id),
+            ) else {
+                continue;
+            };
+            if let Some(endpoints) = endpoints.iter_mut().find(|endpoints| endpoints.module == module) {
+                endpoints.channels.push(ChannelEndpoint {
+                    counterparty,
+                    counterparty_chain_id: other.chain_id.to_string(),
+                    port: side.port.to_string(),
+                    channel: side.channel.to_string(),
+                    counterparty_channel: other.channel.to_string(),
+                });
+            }
+        }
+    }
+
+    endpoints
+}
+
+/// The endpoints as environment variables named `<prefix><MODULE>_<FIELD>`, eg. `VITE_GAIA_RPC_URL`
+pub fn env_variables(endpoints: &[ModuleEndpoints], prefix: &str) -> Vec<(String, String)> {
+    let mut variables = Vec::new();
+    for module in endpoints {
+        let name = |field: &str| env_name(&format!("{}{}_{}", prefix, module.module, field));
+        let fields = [
+            ("CHAIN_ID", &module.chain_id),
+            ("NATIVE_DENOM", &module.native_denom),
+            ("FEE_DENOM", &module.fee_denom),
+            ("RPC_URL", &module.rpc),
+            ("REST_URL", &module.rest),
+            ("GRPC_URL", &module.grpc),
+        ];
+        for (field, value) in fields {
+            if let Some(value) = value {
+                variables.push((name(field), value.clone()));
+            }
+        }
+        for (service, url) in &module.services {
+            variables.push((name(&format!("{}_URL", service)), url.clone()));
+        }
+        for channel in &module.channels {
+            let suffix = match channel.port.as_str() {
+                "transfer" => String::new(),
+                port => format!("_{}", port),
+            };
+            variables.push((name(&format!("CHANNEL_{}{}", channel.counterparty, suffix)), channel.channel.clone()));
+        }
+    }
+    variables
+}
+
+// One end of a channel
+struct ChannelSide<'a> {
+    hostname: Option<&'a str>,
+    chain_id: &'a str,
+    port: &'a str,
+    channel: &'a str,
+}
+
+fn channel_sides(result: &ChannelResult) -> [ChannelSide<'_>; 2] {
+    [
+        ChannelSide {
+            hostname: result.a_hostname.as_deref(),
+            chain_id: &result.a_chain_id,
+            port: &result.a_port,
+            channel: &result.a_channel,
+        },
+        ChannelSide {
+            hostname: result.b_hostname.as_deref(),
+            chain_id: &result.b_chain_id,
+            port: &result.b_port,
+            channel: &result.b_channel,
+        },
+    ]
+}
+
+// Environment variable names only use uppercase letters, digits and underscores
+fn env_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect()
+}
+
+// Hostname, chain id and fee denom of each chain in the live config of Hermes, which init-channels.sh completes with
+// the values it queries from the chains
+fn hermes_chains(hermes_container: &str) -> Vec<(String, String, String)> {
+    let Ok(output) = Command::new("docker")
+        .arg("exec")
+        .arg(hermes_container)
+        .arg("cat")
+        .arg("/root/.hermes/config.toml")
+        .output()
+    else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+    let Ok(config) = String::from_utf8_lossy(&output.stdout).parse::<toml::Table>() else {
+        return Vec::new();
+    };
+
+    let host = Regex::new(r"^\w+://([^:/]+)").unwrap();
+    config
+        .get("chains")
+        .and_then(|chains| chains.as_array())
+        .map(|chains| {
+            chains
+                .iter()
+                .filter_map(|chain| {
+                    let rpc_addr = chain.get("rpc_addr")?.as_str()?;
+                    let hostname = host.captures(rpc_addr)?.get(1)?.as_str().to_string();
+                    let chain_id = chain.get("id")?.as_str()?.to_string();
+                    let denom = chain.get("gas_price")?.get("denom")?.as_str()?.to_string();
+                    Some((hostname, chain_id, denom))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// A variable of a module: its value in the module's .env file, or else its default in the compose file
+fn module_variable(deployment: &Deployment, module: &DeployedModule, name: &str) -> Option<String> {
+    let module_dir = deployment.dir.join(&module.dir);
+    let prefix = format!("{}=", name);
+    let env_value = fs::read_to_string(module_dir.join(".env")).ok().and_then(|content| {
+        content.lines().find_map(|line| {
+            let value = line.strip_prefix(&prefix)?;
+            Some(value.trim_matches('"').replace("\\\"", "\"").replace("\\\\", "\\"))
+        })
+    });
+    env_value.or_else(|| {
+        let content = fs::read_to_string(deployment.compose_file(module)).ok()?;
+        let re = Regex::new(&format!(r"\$\{{{}:?-([^}}]*)\}}", regex::escape(name))).unwrap();
+        re.captures(&content).map(|caps| caps[1].to_string())
+    })
+}
@@ -1,66 +1,329 @@
+use crate::commands::status::chain_status;
+use crate::deployment::Deployment;
 use crate::error::AppError;
+use crate::utils::container_state;
+use crate::ChannelsOutput;
+use serde::Serialize;
 use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
 use std::path::Path;
 use std::process::Command;
 use colored::Colorize;
 
-pub fn handle_ibc_channels(args: crate::DeploymentArgs) -> Result<(), AppError> {
+pub fn handle_ibc_channels(args: crate::IbcChannelsArgs) -> Result<(), AppError> {
+    let deployment = Deployment::load(Path::new(&args.deployment_dir))?;
+    let hermes_container = deployment.hermes_container()?;
+    let hermes_state = container_state(hermes_container);
+
+    // The results stay in the Hermes container while it is stopped; only the channels without one are pending
+    let results = match hermes_state {
+        Some(_) => channel_results(hermes_container)?,
+        None => Vec::new(),
+    };
+    let pending: Vec<ExpectedLink> = expected_links(&deployment)?
+        .into_iter()
+        .filter(|link| !results.iter().any(|result| link.matches(result)))
+        .collect();
+
+    if args.output != ChannelsOutput::Table {
+        let report = ChannelsReport {
+            hermes: HermesReport { container: hermes_container.to_string(), state: hermes_state },
+            channels: results
+                .iter()
+                .map(ChannelReport::from_result)
+                .chain(pending.iter().map(ChannelReport::pending))
+                .collect(),
+        };
+        let output = match args.output {
+            ChannelsOutput::Json => serde_json::to_string_pretty(&report).map_err(|err| err.to_string()),
+            _ => serde_yaml::to_string(&report).map_err(|err| err.to_string()),
+        }
+        .map_err(|err| AppError::InvalidConfig(format!("Failed to serialize channels: {}", err)))?;
+        println!("{}", output.trim_end());
+        return Ok(());
+    }
+
     println!(
         "Listing created IBC channels for deployment in directory: {}",
         args.deployment_dir
     );
 
-    let deployment_dir = Path::new(&args.deployment_dir);
-    let project_name = deployment_dir.file_name().unwrap().to_str().unwrap();
-    let hermes_container = format!("{}-hermes-1", project_name);
+    if results.is_empty() && pending.is_empty() {
+        println!("No IBC channel JSON files found in Hermes container. NOTE: Channel creation may take several minutes per channel; wait a few minutes and try this command again.");
+        return Ok(());
+    }
 
-    // Channel json files are expected to be in /root/.hermes/ of Hermes container with filenames in the format ${chain_id_a}_${chain_id_b}.json
-    let ls_output = Command::new("docker")
-        .arg("exec")
-        .arg(&hermes_container)
-        .arg("sh")
-        .arg("-c")
-        .arg("ls /root/.hermes/*.json")
-        .output()
-        .map_err(AppError::Io)?;
+    for result in results {
+        let chain_a_id = result.a_chain_id.as_str();
+        let chain_b_id = result.b_chain_id.as_str();
+        let width = std::cmp::max(chain_a_id.len(), chain_b_id.len());
 
-    if !ls_output.status.success() {
-        println!("No IBC channel JSON files found in Hermes container. NOTE: Channel creation may take several minutes per channel; wait a few minutes and try this command again.");
-        return Err(AppError::DockerCommand(
-            "Failed to list IBC JSON files in Hermes container".into(),
-        ));
+        println!("\n{}", format!(
+            "{:<width$} {:^14} {:<width$}",
+            chain_a_id, "<------>", chain_b_id,
+            width = width)
+            .yellow().bold()
+        );
+        println!(
+            "{:<width$} {:^14} {:<width$}",
+            result.a_channel, "", result.b_channel,
+            width = width
+        );
+        println!(
+            "{:<width$} {:^14} {:<width$}",
+            result.a_client, "", result.b_client,
+            width = width
+        );
+        if result.a_port != "transfer" || result.b_port != "transfer" {
+            println!(
+                "{:<width$} {:^14} {:<width$}",
+                result.a_port, "", result.b_port,
+                width = width
+            );
+        }
+        match result.status.as_str() {
+            "success" => println!("Status: {}", "Success".green()),
+            "error" => println!("Status: {}", "Failed".red()),
+            "skipped" => println!("Status: {} ({})", "Skipped".red(), result.error.as_deref().unwrap_or_default()),
+            _ => println!("Status: {}", "Unknown".yellow()),
+        }
     }
 
-    let json_files = String::from_utf8(ls_output.stdout)
-        .map_err(|_| AppError::InvalidConfig("Non-UTF8 filenames found".into()))?
-        .lines()
-        .map(String::from)
-        .collect::<Vec<_>>();
+    // Channels Hermes has not finished creating are shown by the hostnames of their chains
+    for link in pending {
+        let width = std::cmp::max(link.a_hostname.len(), link.b_hostname.len());
+        println!("\n{}", format!(
+            "{:<width$} {:^14} {:<width$}",
+            link.a_hostname, "<------>", link.b_hostname,
+            width = width)
+            .yellow().bold()
+        );
+        if link.a_port != "transfer" || link.b_port != "transfer" {
+            println!(
+                "{:<width$} {:^14} {:<width$}",
+                link.a_port, "", link.b_port,
+                width = width
+            );
+        }
+        println!("Status: {}", "Pending".yellow());
+    }
 
-    if json_files.is_empty() {
-        println!("No IBC channel JSON files found in Hermes container. NOTE: Channel creation may take several minutes per channel; wait a few minutes and try this command again.");
-        return Ok(());
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ChannelsReport {
+    hermes: HermesReport,
+    channels: Vec<ChannelReport>,
+}
+
+#[derive(Serialize)]
+struct HermesReport {
+    container: String,
+    /// None if the container does not exist
+    state: Option<String>,
+}
+
+/// A channel as reported by `ibc-channels --output json|yaml`
+#[derive(Serialize)]
+struct ChannelReport {
+    a: ChannelEndReport,
+    b: ChannelEndReport,
+    /// `success`, `failed`, `skipped` or `pending`
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// One end of a channel; ids are None until they are known
+#[derive(Serialize)]
+struct ChannelEndReport {
+    chain_id: Option<String>,
+    hostname: Option<String>,
+    port: String,
+    channel: Option<String>,
+    client: Option<String>,
+    connection: Option<String>,
+}
+
+impl ChannelReport {
+    fn from_result(result: &ChannelResult) -> ChannelReport {
+        let known = |value: &str| (value != "-").then(|| value.to_string());
+        let end = |chain_id: &str, hostname: &Option<String>, port: &str, channel: &str, client: &str, connection: &str| {
+            ChannelEndReport {
+                chain_id: (chain_id != "none").then(|| chain_id.to_string()),
+                hostname: hostname.clone(),
+                port: port.to_string(),
+                channel: known(channel),
+                client: known(client),
+                connection: known(connection),
+            }
+        };
+        ChannelReport {
+            a: end(&result.a_chain_id, &result.a_hostname, &result.a_port, &result.a_channel, &result.a_client, &result.a_connection),
+            b: end(&result.b_chain_id, &result.b_hostname, &result.b_port, &result.b_channel, &result.b_client, &result.b_connection),
+            status: match result.status.as_str() {
+                "success" => "success",
+                "skipped" => "skipped",
+                _ => "failed",
+            },
+            error: (result.status != "success").then(|| result.error.clone()).flatten(),
+        }
     }
 
-    for json_file in json_files {
+    fn pending(link: &ExpectedLink) -> ChannelReport {
+        let end = |chain_id: &Option<String>, hostname: &str, port: &str| ChannelEndReport {
+            chain_id: chain_id.clone(),
+            hostname: Some(hostname.to_string()),
+            port: port.to_string(),
+            channel: None,
+            client: None,
+            connection: None,
+        };
+        ChannelReport {
+            a: end(&link.a_chain_id, &link.a_hostname, &link.a_port),
+            b: end(&link.b_chain_id, &link.b_hostname, &link.b_port),
+            status: "pending",
+            error: None,
+        }
+    }
+}
+
+/// The outcome of creating one channel, as saved by the Hermes module's init-channels.sh
+pub struct ChannelResult {
+    pub a_chain_id: String,
+    pub b_chain_id: String,
+    /// Hostnames of the chains; missing from the results of deployments created by older versions
+    pub a_hostname: Option<String>,
+    pub b_hostname: Option<String>,
+    pub a_port: String,
+    pub b_port: String,
+    pub a_channel: String,
+    pub b_channel: String,
+    pub a_client: String,
+    pub b_client: String,
+    pub a_connection: String,
+    pub b_connection: String,
+    /// `success`, `error`, or `skipped` when a chain was not available to init-channels.sh
+    pub status: String,
+    /// The error message of a failed or skipped channel
+    pub error: Option<String>,
+}
+
+/// Read the channel creation results from the Hermes container. Results are only written once a channel has been
+/// created (or has failed), so channels that are still being created are missing
+pub fn channel_results(hermes_container: &str) -> Result<Vec<ChannelResult>, AppError> {
+    let mut results = Vec::new();
+    for (json_file, file_contents) in result_files(hermes_container)? {
         // Extract chain IDs from the filename
         let filename = Path::new(&json_file)
             .file_name()
             .and_then(|name| name.to_str())
             .ok_or(AppError::InvalidConfig("Invalid JSON filename".into()))?;
 
+        // Links on other ports than transfer have the port id appended to the filename: ${chain_id_a}_${chain_id_b}@${port_a}.json
+        let link_name = filename.trim_end_matches(".json");
+        let chain_ids = link_name.split_once('@').map_or(link_name, |(chain_ids, _)| chain_ids);
         let (chain_a_id, chain_b_id) =
-            filename
-                .trim_end_matches(".json")
+            chain_ids
                 .split_once('_')
                 .ok_or(AppError::InvalidConfig(
                     "Invalid JSON filename format".into(),
                 ))?;
 
+        // Parse json for channel and client IDs
+        let parsed_json: Value = serde_json::from_str(&file_contents)
+            .map_err(|_| AppError::InvalidConfig("Invalid JSON format".into()))?;
+
+        // A failed channel has an error message as its result, so only the ports added by init-channels.sh are known.
+        // Skipped channels are named after their hostnames, and carry their chain ids (`none` for unavailable chains)
+        let optional = |pointer: &str| parsed_json.pointer(pointer).and_then(Value::as_str).map(String::from);
+        let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        results.push(ChannelResult {
+            a_chain_id: optional("/a_chain_id").unwrap_or_else(|| chain_a_id.to_string()),
+            b_chain_id: optional("/b_chain_id").unwrap_or_else(|| chain_b_id.to_string()),
+            a_hostname: optional("/a_hostname"),
+            b_hostname: optional("/b_hostname"),
+            a_port: optional("/result/a_side/port_id").or(optional("/a_port")).unwrap_or_else(|| "transfer".to_string()),
+            b_port: optional("/result/b_side/port_id").or(optional("/b_port")).unwrap_or_else(|| "transfer".to_string()),
+            a_channel: or_dash(optional("/result/a_side/channel_id")),
+            b_channel: or_dash(optional("/result/b_side/channel_id")),
+            a_client: or_dash(optional("/result/a_side/client_id")),
+            b_client: or_dash(optional("/result/b_side/client_id")),
+            a_connection: or_dash(optional("/result/a_side/connection_id")),
+            b_connection: or_dash(optional("/result/b_side/connection_id")),
+            status: parse_json_value(&parsed_json, "/status", "success")?.to_string(),
+            error: optional("/result"),
+        });
+    }
+
+    Ok(results)
+}
+
+// The paths and contents of the channel json files of the Hermes container. A stopped container cannot run commands,
+// so its files are copied out of it instead
+fn result_files(hermes_container: &str) -> Result<Vec<(String, String)>, AppError> {
+    let running = container_state(hermes_container).is_some_and(|state| state.starts_with("running"));
+    if !running {
+        let copy_dir = env::temp_dir().join(format!("nibc-forge-{}-{}", hermes_container, std::process::id()));
+        let cp_output = Command::new("docker")
+            .arg("cp")
+            .arg(format!("{}:/root/.hermes", hermes_container))
+            .arg(&copy_dir)
+            .output()
+            .map_err(AppError::Io)?;
+        if !cp_output.status.success() {
+            let _ = fs::remove_dir_all(&copy_dir);
+            return Err(AppError::DockerCommand(format!(
+                "Failed to copy IBC JSON files from Hermes container: {}",
+                String::from_utf8_lossy(&cp_output.stderr).trim()
+            )));
+        }
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&copy_dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                if let Ok(content) = fs::read_to_string(&path) {
+                    files.push((path.to_string_lossy().into_owned(), content));
+                }
+            }
+        }
+        fs::remove_dir_all(&copy_dir).map_err(AppError::Io)?;
+        return Ok(files);
+    }
+
+    // Channel json files are expected to be in /root/.hermes/ of Hermes container with filenames in the format ${chain_id_a}_${chain_id_b}.json
+    let ls_output = Command::new("docker")
+        .arg("exec")
+        .arg(hermes_container)
+        .arg("sh")
+        .arg("-c")
+        .arg("ls /root/.hermes/*.json 2>/dev/null || true")
+        .output()
+        .map_err(AppError::Io)?;
+
+    if !ls_output.status.success() {
+        return Err(AppError::DockerCommand(format!(
+            "Failed to list IBC JSON files in Hermes container: {}",
+            String::from_utf8_lossy(&ls_output.stderr).trim()
+        )));
+    }
+
+    let json_files = String::from_utf8(ls_output.stdout)
+        .map_err(|_| AppError::InvalidConfig("Non-UTF8 filenames found".into()))?
+        .lines()
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    let mut files = Vec::new();
+    for json_file in json_files {
         // Read the json file
         let cat_output = Command::new("docker")
             .arg("exec")
-            .arg(&hermes_container)
+            .arg(hermes_container)
             .arg("cat")
             .arg(&json_file)
             .output()
@@ -73,43 +336,117 @@ pub fn handle_ibc_channels(args: crate::DeploymentArgs) -> Result<(), AppError>
 
         let file_contents = String::from_utf8(cat_output.stdout)
             .map_err(|_| AppError::InvalidConfig("Non-UTF8 JSON content".into()))?;
+        files.push((json_file, file_contents));
+    }
 
-        // Parse json for channel and client IDs
-        let parsed_json: Value = serde_json::from_str(&file_contents)
-            .map_err(|_| AppError::InvalidConfig("Invalid JSON format".into()))?;
+    Ok(files)
+}
 
-        let chain_a_channel = parse_json_value(&parsed_json, "/result/a_side/channel_id", "chain_a_channel")?;
-        let chain_b_channel = parse_json_value(&parsed_json, "/result/b_side/channel_id", "chain_b_channel")?;
-        let chain_a_client = parse_json_value(&parsed_json, "/result/a_side/client_id", "chain_a_client")?;
-        let chain_b_client = parse_json_value(&parsed_json, "/result/b_side/client_id", "chain_b_client")?;
-        let success = parse_json_value(&parsed_json, "/status", "success")?;
-
-		let width = std::cmp::max(chain_a_id.len(), chain_b_id.len());
-
-		println!("\n{}", format!(
-			"{:<width$} {:^14} {:<width$}", 
-			chain_a_id, "<------>", chain_b_id, 
-			width = width)
-			.yellow().bold()
-		);
-		println!(
-			"{:<width$} {:^14} {:<width$}",
-			chain_a_channel, "", chain_b_channel,
-			width = width
-		);
-		println!(
-			"{:<width$} {:^14} {:<width$}",
-			chain_a_client, "", chain_b_client,
-			width = width
-		);
-        match success {
-            "success" => println!("Status: {}", "Success".green()),
-            "error" => println!("Status: {}", "Failed".red()),
-            _ => println!("Status: {}", "Unknown".yellow()),
-        }
+/// A channel Hermes is expected to create
+pub struct ExpectedLink {
+    pub a_hostname: String,
+    pub b_hostname: String,
+    /// Chain ids of the chains, as reported by their RPC; None while a chain is not answering
+    pub a_chain_id: Option<String>,
+    pub b_chain_id: Option<String>,
+    pub a_port: String,
+    pub b_port: String,
+}
+
+impl ExpectedLink {
+    /// Results of deployments created by older versions have no hostnames, and are matched by chain id instead
+    pub fn matches(&self, result: &ChannelResult) -> bool {
+        let side_matches = |hostname: &Option<String>, chain_id: &str, link_hostname: &str, link_chain_id: &Option<String>| {
+            match hostname {
+                Some(hostname) => hostname == link_hostname,
+                None => link_chain_id.as_deref() == Some(chain_id),
+            }
+        };
+        side_matches(&result.a_hostname, &result.a_chain_id, &self.a_hostname, &self.a_chain_id)
+            && side_matches(&result.b_hostname, &result.b_chain_id, &self.b_hostname, &self.b_chain_id)
+            && result.a_port == self.a_port
+            && result.b_port == self.b_port
     }
+}
 
-    Ok(())
+/// The channels the Hermes module of a deployment will create: the links in its chainlist.json, or else the channels
+/// of its TOPOLOGY, the same way init-channels.sh decides
+pub fn expected_links(deployment: &Deployment) -> Result<Vec<ExpectedLink>, AppError> {
+    let hermes = deployment
+        .hermes_module()
+        .ok_or_else(|| AppError::InvalidConfig("The deployment has no hermes module".to_string()))?;
+    let hermes_dir = deployment.dir.join(&hermes.dir);
+
+    let chainlist_path = hermes_dir.join("chainlist.json");
+    let chainlist: Value = fs::read_to_string(&chainlist_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .ok_or_else(|| AppError::InvalidConfig(format!("Failed to read {}", chainlist_path.display())))?;
+
+    // The chain ids of the running chains, by hostname
+    let chain_ids: BTreeMap<String, String> = deployment
+        .modules
+        .iter()
+        .filter(|module| module.kind.is_chain())
+        .filter_map(|module| Some((module.hostname.clone()?, chain_status(module).chain_id?)))
+        .collect();
+    let chain_id = |hostname: &str| chain_ids.get(hostname).cloned();
+
+    let links = chainlist.get("links").and_then(Value::as_array).cloned().unwrap_or_default();
+    if !links.is_empty() {
+        return Ok(links
+            .iter()
+            .map(|link| {
+                let field = |name: &str| link.get(name).and_then(Value::as_str).unwrap_or("transfer").to_string();
+                ExpectedLink {
+                    a_chain_id: chain_id(&field("a_chain")),
+                    b_chain_id: chain_id(&field("b_chain")),
+                    a_hostname: field("a_chain"),
+                    b_hostname: field("b_chain"),
+                    a_port: field("a_port"),
+                    b_port: field("b_port"),
+                }
+            })
+            .collect());
+    }
+
+    let hostnames: Vec<String> = chainlist
+        .get("chains")
+        .and_then(Value::as_array)
+        .map(|chains| {
+            chains
+                .iter()
+                .filter_map(|chain| chain.get("hostname").and_then(Value::as_str).map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // TOPOLOGY is set through the module's .env file, and defaults to hub
+    let env_content = fs::read_to_string(hermes_dir.join(".env")).unwrap_or_default();
+    let mesh = env_content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .any(|(name, value)| name.trim() == "TOPOLOGY" && value.trim().trim_matches('"') == "mesh");
+
+    let mut pairs = Vec::new();
+    for i in 0..hostnames.len() {
+        for j in i + 1..hostnames.len() {
+            if mesh || i == 0 {
+                pairs.push((i, j));
+            }
+        }
+    }
+    Ok(pairs
+        .into_iter()
+        .map(|(i, j)| ExpectedLink {
+            a_chain_id: chain_id(&hostnames[i]),
+            b_chain_id: chain_id(&hostnames[j]),
+            a_hostname: hostnames[i].clone(),
+            b_hostname: hostnames[j].clone(),
+            a_port: "transfer".to_string(),
+            b_port: "transfer".to_string(),
+        })
+        .collect())
 }
 
 fn parse_json_value<'a>(
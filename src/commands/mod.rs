@@ -5,3 +5,12 @@ pub mod clean;
 pub mod list;
 pub mod ibc_channels;
 pub mod dump_db;
+pub mod validate;
+pub mod prune;
+pub mod status;
+pub mod wait;
+pub mod up;
+pub mod reset;
+pub mod snapshot;
+pub mod clone;
+pub mod endpoints;
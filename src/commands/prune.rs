@@ -0,0 +1,156 @@
+use crate::deployment::{Deployment, DEPLOYMENT_LABEL, DIR_LABEL};
+use crate::error::AppError;
+use crate::utils::{confirm, labeled_resources, remove_resources, RESOURCE_TYPES};
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+/// The resources of one deployment, as found by their nibc-forge labels
+#[derive(Default)]
+struct ResourceGroup {
+    dir: String,
+    resources: Vec<(&'static str, String)>,
+}
+
+pub fn handle_prune(args: crate::PruneArgs) -> Result<(), AppError> {
+    println!("Looking for resources of deleted nibc-forge deployments");
+
+    let mut groups: BTreeMap<String, ResourceGroup> = BTreeMap::new();
+    for resource_type in RESOURCE_TYPES {
+        for (name, project, dir) in nibc_forge_resources(resource_type)? {
+            let group = groups.entry(project).or_default();
+            if group.dir.is_empty() {
+                group.dir = dir;
+            }
+            group.resources.push((resource_type, name));
+        }
+    }
+
+    if groups.is_empty() {
+        println!("No nibc-forge resources found.");
+        return Ok(());
+    }
+
+    let mut orphaned = Vec::new();
+    for (project, group) in &groups {
+        let is_orphaned = !deployment_exists(project, &group.dir);
+        let status = if is_orphaned { "orphaned".red() } else { "in use".green() };
+        let dir = if group.dir.is_empty() { "unknown directory" } else { group.dir.as_str() };
+
+        println!("\n{} ({}): {}", project.yellow().bold(), dir, status);
+        for (resource_type, name) in &group.resources {
+            println!("  {:<10} {}", resource_type, name);
+        }
+
+        if is_orphaned {
+            orphaned.extend(group.resources.iter().map(|(resource_type, name)| (*resource_type, name.as_str())));
+        }
+    }
+
+    if orphaned.is_empty() {
+        println!("\nNo orphaned resources found.");
+        return Ok(());
+    }
+    if args.dry_run {
+        println!("\nDry run: {} orphaned resource(s) would be removed.", orphaned.len());
+        return Ok(());
+    }
+    if !args.yes && !confirm(&format!("\nRemove {} orphaned resource(s)?", orphaned.len()))? {
+        println!("Prune aborted; nothing was removed.");
+        return Ok(());
+    }
+
+    // Remove all containers of every group before any network or volume
+    orphaned.sort_by_key(|(resource_type, _)| RESOURCE_TYPES.iter().position(|t| t == resource_type));
+    let failed = remove_resources(&orphaned)?;
+    if failed > 0 {
+        return Err(AppError::DockerCommand(format!("Failed to remove {} resource(s)", failed)));
+    }
+
+    println!("Removed {} orphaned resource(s).", orphaned.len());
+    Ok(())
+}
+
+/// Name, project and deployment directory of every container, network, volume or image carrying the nibc-forge label
+fn nibc_forge_resources(resource_type: &'static str) -> Result<Vec<(String, String, String)>, AppError> {
+    if resource_type == "image" {
+        return nibc_forge_images();
+    }
+    let name_format = match resource_type {
+        "container" => "{{.Names}}",
+        _ => "{{.Name}}",
+    };
+    let mut ls_command = Command::new("docker");
+    ls_command.arg(resource_type).arg("ls");
+    if resource_type == "container" {
+        ls_command.arg("--all");
+    }
+    let output = ls_command
+        .arg("--filter")
+        .arg(format!("label={}", DEPLOYMENT_LABEL))
+        .arg("--format")
+        .arg(format!(
+            "{}\t{{{{.Label \"{}\"}}}}\t{{{{.Label \"{}\"}}}}",
+            name_format, DEPLOYMENT_LABEL, DIR_LABEL
+        ))
+        .output()
+        .map_err(AppError::Io)?;
+
+    if !output.status.success() {
+        return Err(AppError::DockerCommand(format!(
+            "Failed to list {}s: {}",
+            resource_type,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?.to_string();
+            let project = fields.next()?.to_string();
+            let dir = fields.next().unwrap_or_default().to_string();
+            Some((name, project, dir))
+        })
+        .collect())
+}
+
+// Images cannot be listed along with their labels, so each one is inspected for them
+fn nibc_forge_images() -> Result<Vec<(String, String, String)>, AppError> {
+    let mut images = Vec::new();
+    for name in labeled_resources("image", DEPLOYMENT_LABEL)? {
+        let output = Command::new("docker")
+            .arg("image")
+            .arg("inspect")
+            .arg("--format")
+            .arg(format!(
+                "{{{{index .Config.Labels \"{}\"}}}}\t{{{{index .Config.Labels \"{}\"}}}}",
+                DEPLOYMENT_LABEL, DIR_LABEL
+            ))
+            .arg(&name)
+            .output()
+            .map_err(AppError::Io)?;
+        if !output.status.success() {
+            return Err(AppError::DockerCommand(format!(
+                "Failed to inspect image {}: {}",
+                name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let labels = String::from_utf8_lossy(&output.stdout);
+        let (project, dir) = labels.trim_end().split_once('\t').unwrap_or((labels.trim_end(), ""));
+        images.push((name, project.to_string(), dir.to_string()));
+    }
+    Ok(images)
+}
+
+// A deployment still exists if its directory holds a manifest for the same project; a directory that was deleted and
+// created again for another deployment does not count
+fn deployment_exists(project: &str, dir: &str) -> bool {
+    if dir.is_empty() {
+        return false;
+    }
+    Deployment::load(Path::new(dir)).is_ok_and(|deployment| deployment.project == project)
+}
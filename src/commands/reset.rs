@@ -0,0 +1,98 @@
+use crate::commands::snapshot::snapshot_image_repository;
+use crate::commands::start::handle_start;
+use crate::deployment::{DeployedModule, Deployment, DEPLOYMENT_LABEL, SNAPSHOT_COMPOSE_FILE};
+use crate::error::AppError;
+use crate::utils::{confirm, labeled_resources, remove_resources};
+use colored::Colorize;
+use std::fs;
+use std::path::Path;
+
+pub fn handle_reset(args: crate::ResetArgs) -> Result<(), AppError> {
+    println!("Resetting deployment in directory: {}", args.deployment_dir);
+
+    let deployment = Deployment::load(Path::new(&args.deployment_dir))?;
+    deployment.check_project()?;
+
+    // The Hermes state (its config and the channels it created) only lives in its container, which is removed along
+    // with any chain so that the channels are created again once it has restarted. Resetting other modules leaves the
+    // channels alone
+    let mut modules: Vec<&DeployedModule> = deployment.select_modules(&args.modules)?;
+    if let Some(hermes) = deployment.hermes_module() {
+        let resets_chain = modules.iter().any(|module| module.kind.is_chain());
+        if resets_chain && !modules.iter().any(|module| module.name == hermes.name) {
+            modules.push(hermes);
+        }
+    }
+
+    // Only remove the resources that exist and carry the deployment's label
+    let label = format!("{}={}", DEPLOYMENT_LABEL, deployment.project);
+    let containers = labeled_resources("container", &label)?;
+    let volumes = labeled_resources("volume", &label)?;
+    let mut resources = Vec::new();
+    for module in &modules {
+        for service in &module.services {
+            if containers.contains(&service.container) {
+                resources.push(("container", service.container.clone()));
+            }
+        }
+    }
+    for module in &modules {
+        for (_, volume) in deployment.module_volumes(module)? {
+            if volumes.contains(&volume) {
+                resources.push(("volume", volume));
+            }
+        }
+    }
+    // The modules go back to their original images; `restore` loads the snapshot images again from the snapshot
+    let images = labeled_resources("image", &label)?;
+    for module in &modules {
+        for service in &module.services {
+            let prefix = format!("{}:", snapshot_image_repository(&deployment.project, &service.name));
+            for image in images.iter().filter(|image| image.starts_with(&prefix)) {
+                resources.push(("image", image.clone()));
+            }
+        }
+    }
+
+    let names: Vec<&str> = modules.iter().map(|module| module.name.as_str()).collect();
+    println!("Modules to reset: {}", names.join(", "));
+    if resources.is_empty() {
+        println!("No containers, volumes or images found for these modules.");
+    } else {
+        println!("\n{}", "The following resources will be removed:".yellow().bold());
+        for (resource_type, name) in &resources {
+            println!("{:<10} {}", resource_type, name);
+        }
+        if !args.yes && !confirm("\nContinue?")? {
+            println!("Reset aborted; nothing was removed.");
+            return Ok(());
+        }
+    }
+
+    let failed = remove_resources(&resources)?;
+    if failed > 0 {
+        return Err(AppError::DockerCommand(format!(
+            "Failed to remove {} resource(s); the deployment was not started again",
+            failed
+        )));
+    }
+
+    // Modules restored from a snapshot go back to their original images
+    for module in &modules {
+        let snapshot_file = deployment.dir.join(&module.dir).join(SNAPSHOT_COMPOSE_FILE);
+        if snapshot_file.exists() {
+            fs::remove_file(snapshot_file).map_err(AppError::Io)?;
+        }
+    }
+
+    // Starting the modules again re-runs their initialization, as their containers are new
+    handle_start(crate::StartArgs {
+        deployment_dir: args.deployment_dir,
+        modules: if args.modules.is_empty() {
+            Vec::new()
+        } else {
+            names.iter().map(|name| name.to_string()).collect()
+        },
+        timeout: args.timeout,
+    })
+}
@@ -0,0 +1,416 @@
+use crate::commands::start::handle_start;
+use crate::commands::status::chain_status;
+use crate::commands::stop::handle_stop;
+use crate::compose::serialize_compose;
+use crate::deployment::{valid_project_name, Deployment, DEPLOYMENT_LABEL, DIR_LABEL, SNAPSHOT_COMPOSE_FILE};
+use crate::error::AppError;
+use crate::utils::{confirm, labeled_resources, remove_resources};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+/// Directory of the deployment that snapshots are stored in, one subdirectory per snapshot
+pub const SNAPSHOTS_DIR: &str = "snapshots";
+const SNAPSHOT_FILE: &str = "snapshot.toml";
+const IMAGES_FILE: &str = "images.tar";
+const VOLUMES_DIR: &str = "volumes";
+/// Image of the short-lived containers that copy volume contents to and from the snapshot
+const VOLUME_HELPER_IMAGE: &str = "busybox:stable";
+
+/// Record of a snapshot, written next to its image and volume tarballs
+#[derive(Deserialize, Serialize)]
+pub struct Snapshot {
+    pub name: String,
+    /// Project of the deployment the snapshot was taken of
+    pub project: String,
+    /// Seconds since the Unix epoch
+    pub created: u64,
+    pub modules: Vec<SnapshotModule>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SnapshotModule {
+    pub name: String,
+    /// Latest block height of a chain when the snapshot was taken, if its RPC answered
+    pub height: Option<u64>,
+    /// Image committed from the container of each service
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub images: BTreeMap<String, String>,
+    /// Volumes mounted in the module's containers that were saved, keyed by service and mount path (eg.
+    /// `postgres:/var/lib/postgresql/data`), with the docker name their tarball is named after
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub volumes: BTreeMap<String, String>,
+}
+
+pub fn snapshot_dir(deployment: &Deployment, name: &str) -> PathBuf {
+    deployment.dir.join(SNAPSHOTS_DIR).join(name)
+}
+
+/// Repository of the images committed from a service's container, tagged with the snapshot name
+pub fn snapshot_image_repository(project: &str, service: &str) -> String {
+    format!("nibc-forge-{}-{}", project, service.to_lowercase())
+}
+
+impl Snapshot {
+    pub fn load(deployment: &Deployment, name: &str) -> Result<Snapshot, AppError> {
+        let path = snapshot_dir(deployment, name).join(SNAPSHOT_FILE);
+        if !path.exists() {
+            let mut known: Vec<String> = fs::read_dir(deployment.dir.join(SNAPSHOTS_DIR))
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .filter(|entry| entry.path().join(SNAPSHOT_FILE).exists())
+                        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                        .collect()
+                })
+                .unwrap_or_default();
+            known.sort();
+            return Err(AppError::InvalidConfig(format!(
+                "No snapshot {} in {}; its snapshots are: [{}]",
+                name,
+                deployment.dir.display(),
+                known.join(", ")
+            )));
+        }
+        let content = fs::read_to_string(&path).map_err(AppError::Io)?;
+        toml::from_str(&content)
+            .map_err(|err| AppError::InvalidConfig(format!("Failed to parse {}: {}", path.display(), err)))
+    }
+}
+
+pub fn handle_snapshot(args: crate::SnapshotArgs) -> Result<(), AppError> {
+    let deployment = Deployment::load(Path::new(&args.deployment_dir))?;
+    deployment.check_project()?;
+    if args.delete {
+        delete_snapshot(&deployment, &args.name)?;
+        println!("{} {}", "Deleted snapshot".green().bold(), args.name);
+        return Ok(());
+    }
+    let absolute_dir = absolute(&deployment.dir)?;
+    take_snapshot(&deployment, &args.name, &deployment.project, &absolute_dir)?;
+
+    println!("\n{} {}", "Snapshot created:".green().bold(), snapshot_dir(&deployment, &args.name).display());
+    println!("The deployment was stopped; run `nibc-forge start` to continue using it, or `nibc-forge restore --name {}` to return to the snapshot later.", args.name);
+    Ok(())
+}
+
+/// Stop the deployment and save the state of its containers and volumes under the deployment directory. The committed
+/// images are named after and labelled with the project and directory of the deployment that will run from them, which
+/// is another one when cloning, so that `clean` and `prune` remove them along with it
+pub fn take_snapshot(
+    deployment: &Deployment,
+    name: &str,
+    images_project: &str,
+    images_dir: &Path,
+) -> Result<Snapshot, AppError> {
+    if !valid_project_name(name) {
+        return Err(AppError::InvalidConfig(format!(
+            "Invalid snapshot name {}: use lowercase letters, digits, dashes and underscores, starting with a letter or digit",
+            name
+        )));
+    }
+    let dir = snapshot_dir(deployment, name);
+    if dir.exists() {
+        return Err(AppError::InvalidConfig(format!(
+            "Snapshot {} already exists; delete it with `nibc-forge snapshot --delete --name {}` to take it again",
+            name, name
+        )));
+    }
+
+    println!("Taking snapshot {} of deployment in directory: {}", name, deployment.dir.display());
+
+    // Heights are recorded before stopping, while the chains still answer
+    let mut snapshot = Snapshot {
+        name: name.to_string(),
+        project: deployment.project.clone(),
+        created: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default(),
+        modules: deployment
+            .modules
+            .iter()
+            .map(|module| SnapshotModule {
+                name: module.name.clone(),
+                height: if module.kind.is_chain() { chain_status(module).latest_height } else { None },
+                images: BTreeMap::new(),
+                volumes: BTreeMap::new(),
+            })
+            .collect(),
+    };
+
+    handle_stop(crate::StopArgs {
+        deployment_dir: deployment.dir.to_string_lossy().into_owned(),
+        modules: Vec::new(),
+    })?;
+
+    fs::create_dir_all(dir.join(VOLUMES_DIR)).map_err(AppError::Io)?;
+    let result = save_state(deployment, &mut snapshot, &dir, images_project, images_dir);
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&dir);
+    }
+    result?;
+
+    Ok(snapshot)
+}
+
+// Commit every container to an image and save the images to a tarball, and copy every volume mounted in a container to
+// a tarball. `docker commit` leaves out the contents of volumes, including the anonymous ones declared by images
+fn save_state(
+    deployment: &Deployment,
+    snapshot: &mut Snapshot,
+    dir: &Path,
+    images_project: &str,
+    images_dir: &Path,
+) -> Result<(), AppError> {
+    let label = format!("{}={}", DEPLOYMENT_LABEL, deployment.project);
+    let containers = labeled_resources("container", &label)?;
+
+    let mut images = Vec::new();
+    let mut saved_volumes = HashSet::new();
+    for (module, snapshot_module) in deployment.modules.iter().zip(snapshot.modules.iter_mut()) {
+        for service in &module.services {
+            if !containers.contains(&service.container) {
+                return Err(AppError::DockerCommand(format!(
+                    "Container {} of {} does not exist; has the deployment been started?",
+                    service.container, module.name
+                )));
+            }
+            let image = format!("{}:{}", snapshot_image_repository(images_project, &service.name), snapshot.name);
+            println!("Committing {} to {}", service.container, image);
+            docker(&[
+                "commit",
+                "--change",
+                &format!("LABEL {}={}", DEPLOYMENT_LABEL, images_project),
+                "--change",
+                &format!("LABEL {}=\"{}\"", DIR_LABEL, images_dir.display()),
+                &service.container,
+                &image,
+            ])?;
+            snapshot_module.images.insert(service.name.clone(), image.clone());
+            images.push(image);
+
+            for (volume, destination) in container_volumes(&service.container)? {
+                snapshot_module.volumes.insert(format!("{}:{}", service.name, destination), volume.clone());
+                // A volume shared by several containers is only saved once
+                if !saved_volumes.insert(volume.clone()) {
+                    continue;
+                }
+                println!("Saving volume {}", volume);
+                docker(&[
+                    "run",
+                    "--rm",
+                    "-v",
+                    &format!("{}:/volume:ro", volume),
+                    "-v",
+                    &format!("{}:/backup", absolute(dir)?.display()),
+                    VOLUME_HELPER_IMAGE,
+                    "tar",
+                    "-czf",
+                    &format!("/backup/{}/{}.tar.gz", VOLUMES_DIR, volume),
+                    "-C",
+                    "/volume",
+                    ".",
+                ])?;
+            }
+        }
+    }
+
+    if !images.is_empty() {
+        println!("Saving images to {}", dir.join(IMAGES_FILE).display());
+        let mut save_args = vec!["save", "-o"];
+        let images_file = dir.join(IMAGES_FILE);
+        let images_file = images_file.to_string_lossy();
+        save_args.push(&images_file);
+        save_args.extend(images.iter().map(String::as_str));
+        docker(&save_args)?;
+    }
+
+    let content = toml::to_string_pretty(snapshot)
+        .map_err(|err| AppError::InvalidConfig(format!("Failed to serialize snapshot: {}", err)))?;
+    fs::write(dir.join(SNAPSHOT_FILE), content).map_err(AppError::Io)
+}
+
+// Remove a snapshot and the images committed for it, as far as they have not been removed yet
+fn delete_snapshot(deployment: &Deployment, name: &str) -> Result<(), AppError> {
+    let snapshot = Snapshot::load(deployment, name)?;
+    let label = format!("{}={}", DEPLOYMENT_LABEL, deployment.project);
+    let existing = labeled_resources("image", &label)?;
+    let images: Vec<(&str, &String)> = snapshot
+        .modules
+        .iter()
+        .flat_map(|module| module.images.values())
+        .filter(|image| existing.contains(image))
+        .map(|image| ("image", image))
+        .collect();
+
+    let failed = remove_resources(&images)?;
+    if failed > 0 {
+        return Err(AppError::DockerCommand(format!(
+            "Failed to remove {} image(s), which the deployment may still run from; the snapshot was kept",
+            failed
+        )));
+    }
+    fs::remove_dir_all(snapshot_dir(deployment, name)).map_err(AppError::Io)
+}
+
+pub fn handle_restore(args: crate::RestoreArgs) -> Result<(), AppError> {
+    let deployment = Deployment::load(Path::new(&args.deployment_dir))?;
+    deployment.check_project()?;
+    let snapshot = Snapshot::load(&deployment, &args.name)?;
+
+    println!("Restoring snapshot {} of deployment in directory: {}", snapshot.name, args.deployment_dir);
+    if !args.yes && !confirm("The current containers and volumes of the deployment will be removed. Continue?")? {
+        println!("Restore aborted; nothing was removed.");
+        return Ok(());
+    }
+
+    restore_snapshot(&deployment, &snapshot, args.timeout)?;
+
+    println!("\n{} {}", "Restored snapshot".green().bold(), snapshot.name);
+    for module in snapshot.modules.iter().filter(|module| module.height.is_some()) {
+        println!("{} was at height {} when the snapshot was taken", module.name, module.height.unwrap_or_default());
+    }
+    Ok(())
+}
+
+/// Replace the containers and volumes of a deployment with those of a snapshot, and start it. The snapshot may have
+/// been taken of another deployment created from the same modules, as `clone` does
+pub fn restore_snapshot(
+    deployment: &Deployment,
+    snapshot: &Snapshot,
+    timeout: std::time::Duration,
+) -> Result<(), AppError> {
+    let dir = snapshot_dir(deployment, &snapshot.name);
+
+    // Remove the current state
+    let label = format!("{}={}", DEPLOYMENT_LABEL, deployment.project);
+    let mut resources = Vec::new();
+    for resource_type in ["container", "volume"] {
+        for name in labeled_resources(resource_type, &label)? {
+            resources.push((resource_type, name));
+        }
+    }
+    let failed = remove_resources(&resources)?;
+    if failed > 0 {
+        return Err(AppError::DockerCommand(format!("Failed to remove {} resource(s)", failed)));
+    }
+
+    if dir.join(IMAGES_FILE).exists() {
+        println!("Loading images from {}", dir.join(IMAGES_FILE).display());
+        docker(&["load", "-i", &dir.join(IMAGES_FILE).to_string_lossy()])?;
+    }
+
+    let mut restored_volumes = HashSet::new();
+    for module in &deployment.modules {
+        let Some(snapshot_module) = snapshot.modules.iter().find(|other| other.name == module.name) else {
+            return Err(AppError::InvalidConfig(format!(
+                "Snapshot {} has no module {}",
+                snapshot.name, module.name
+            )));
+        };
+        if snapshot_module.images.is_empty() {
+            continue;
+        }
+        let override_file = deployment.dir.join(&module.dir).join(SNAPSHOT_COMPOSE_FILE);
+
+        // Run the services from their snapshot images from now on
+        let mut services = Mapping::new();
+        for (service, image) in &snapshot_module.images {
+            let mut entry = Mapping::new();
+            entry.insert(Value::from("image"), Value::from(image.as_str()));
+            services.insert(Value::from(service.as_str()), Value::Mapping(entry));
+        }
+        let mut compose = Mapping::new();
+        compose.insert(Value::from("services"), Value::Mapping(services));
+        fs::write(&override_file, serialize_compose(&Value::Mapping(compose))?).map_err(AppError::Io)?;
+
+        // Creating the containers also creates the module's volumes, which are then filled from the snapshot
+        let mut create_args = vec!["compose".to_string()];
+        for file in deployment.module_compose_files(module) {
+            create_args.push("-f".to_string());
+            create_args.push(file.to_string_lossy().into_owned());
+        }
+        create_args.extend(["--project-name".to_string(), deployment.project.clone(), "create".to_string()]);
+        docker(&create_args.iter().map(String::as_str).collect::<Vec<_>>())?;
+
+        // Volumes are matched by service and mount path, as their docker names differ in a new container
+        for service in &module.services {
+            for (volume, destination) in container_volumes(&service.container)? {
+                let Some(saved) = snapshot_module.volumes.get(&format!("{}:{}", service.name, destination)) else {
+                    continue;
+                };
+                if !restored_volumes.insert(volume.clone()) {
+                    continue;
+                }
+                println!("Restoring volume {}", volume);
+                docker(&[
+                    "run",
+                    "--rm",
+                    "-v",
+                    &format!("{}:/volume", volume),
+                    "-v",
+                    &format!("{}:/backup:ro", absolute(&dir)?.display()),
+                    VOLUME_HELPER_IMAGE,
+                    "tar",
+                    "-xzf",
+                    &format!("/backup/{}/{}.tar.gz", VOLUMES_DIR, saved),
+                    "-C",
+                    "/volume",
+                ])?;
+            }
+        }
+    }
+
+    handle_start(crate::StartArgs {
+        deployment_dir: deployment.dir.to_string_lossy().into_owned(),
+        modules: Vec::new(),
+        timeout,
+    })
+}
+
+// Names of the volumes mounted in a container, named or anonymous, with the path each is mounted at
+fn container_volumes(container: &str) -> Result<Vec<(String, String)>, AppError> {
+    let output = Command::new("docker")
+        .arg("container")
+        .arg("inspect")
+        .arg("--format")
+        .arg("{{range .Mounts}}{{if eq .Type \"volume\"}}{{.Name}}\t{{.Destination}}\n{{end}}{{end}}")
+        .arg(container)
+        .output()
+        .map_err(AppError::Io)?;
+    if !output.status.success() {
+        return Err(AppError::DockerCommand(format!(
+            "Failed to inspect the volumes of container {}: {}",
+            container,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(volume, destination)| (volume.to_string(), destination.to_string()))
+        .collect())
+}
+
+// Docker needs absolute paths for bind mounts
+fn absolute(path: &Path) -> Result<PathBuf, AppError> {
+    path.canonicalize().map_err(AppError::Io)
+}
+
+fn docker(args: &[&str]) -> Result<(), AppError> {
+    let output = Command::new("docker").args(args).output().map_err(AppError::Io)?;
+    if !output.status.success() {
+        return Err(AppError::DockerCommand(format!(
+            "docker {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
@@ -1,58 +1,108 @@
+use crate::commands::status::chain_status;
+use crate::commands::stop::handle_stop;
 use crate::error::AppError;
-use crate::utils::{deployment_compose_files, list_resources};
-use std::path::Path;
+use crate::deployment::{DeployedModule, Deployment};
+use crate::utils::{container_state, list_resources};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-pub fn handle_start(args: crate::DeploymentArgs) -> Result<(), AppError> {
-    println!("Starting deployment in directory: {}", args.deployment_dir);
-
-    let deployment_dir = Path::new(&args.deployment_dir);
-    let project_name = deployment_dir.file_name().unwrap().to_str().unwrap();
+const READINESS_INTERVAL: Duration = Duration::from_secs(2);
 
-    // Collect all docker-compose.yml files from subdirectories
-    let compose_files = deployment_compose_files(deployment_dir)?;
+#[derive(Clone, Copy, PartialEq)]
+enum ModuleState {
+    Pending,
+    Ready,
+    Failed,
+}
 
-    // Check for running containers associated with this deployment
-    let mut ps_command = Command::new("docker");
-    ps_command.arg("compose");
-    for compose_file in &compose_files {
-        ps_command.arg("-f").arg(compose_file.to_str().unwrap());
-    }
-    ps_command.arg("ps").arg("-q");
+pub fn handle_start(args: crate::StartArgs) -> Result<(), AppError> {
+    println!("Starting deployment in directory: {}", args.deployment_dir);
 
-    let ps_output = ps_command.output().map_err(AppError::Io)?;
+    let deployment = Deployment::load(Path::new(&args.deployment_dir))?;
+    let project_name = deployment.project.as_str();
+    deployment.check_project()?;
+    let selected = deployment.select_modules(&args.modules)?;
+    let compose_files = deployment.compose_files();
 
-    if !ps_output.stdout.is_empty() {
+    // Check for running containers associated with this deployment; starting selected modules is always allowed, as
+    // it is how a stopped module of a running deployment is started again
+    if args.modules.is_empty() && is_running(&compose_files, project_name)? {
         println!("Deployment already running.");
         return Ok(());
     }
 
-    // Start deployment. We start each compose file in a separate command to avoid issues with relative paths in the compose files
-    for compose_file in compose_files.iter() {
-        println!("Using compose file: {}", compose_file.display());
-
-        let mut up_command = Command::new("docker");
-        up_command.arg("compose");
-        up_command
-            .arg("-f")
-            .arg(compose_file.to_str().unwrap())
-            .arg("--project-name")
-            .arg(project_name)
-            .arg("up")
-            .arg("-d");
-
-        // Suppress stderr output to hide the irrelevant warnings about orphaned containers
-        let status = up_command.stderr(Stdio::null()).status().map_err(AppError::Io)?;
-
-        if !status.success() {
-            println!("Failed to start components for compose file: {}. Is there a name, resource or port conflict with another module or a previous deployment?", compose_file.display());
-            println!("You can also run `nibc-forge clean` to remove any associated docker containers, networks, and/or volumes and try again.");
-            return Err(AppError::DockerCommand(
-                format!("docker compose up failed for {}", compose_file.display()).into(),
-            ));
+    // Start every selected module in its own thread, once all of its dependencies are ready, so independent modules
+    // start in parallel. A module whose dependency fails is not started, and neither is one depending on a module that
+    // is not selected and not ready
+    let states: Mutex<HashMap<&str, ModuleState>> = Mutex::new(
+        deployment
+            .modules
+            .iter()
+            .map(|module| {
+                let state = if selected.iter().any(|other| other.name == module.name) {
+                    ModuleState::Pending
+                } else if module_ready(module) {
+                    ModuleState::Ready
+                } else {
+                    ModuleState::Failed
+                };
+                (module.name.as_str(), state)
+            })
+            .collect(),
+    );
+    let state_changed = Condvar::new();
+
+    thread::scope(|scope| {
+        for module in &selected {
+            let module = *module;
+            let (states, state_changed, deployment) = (&states, &state_changed, &deployment);
+            scope.spawn(move || {
+                let dependencies = deployment.dependencies(module);
+                let mut guard = states.lock().unwrap();
+                loop {
+                    let dependency_states: Vec<ModuleState> =
+                        dependencies.iter().map(|dependency| guard[dependency.name.as_str()]).collect();
+                    if dependency_states.contains(&ModuleState::Failed) {
+                        println!("Not starting {}: a module it depends on failed to start or is not running", module.name);
+                        guard.insert(&module.name, ModuleState::Failed);
+                        state_changed.notify_all();
+                        return;
+                    }
+                    if dependency_states.iter().all(|state| *state == ModuleState::Ready) {
+                        break;
+                    }
+                    guard = state_changed.wait(guard).unwrap();
+                }
+                drop(guard);
+
+                let state = match start_module(deployment, module, args.timeout) {
+                    Ok(()) => ModuleState::Ready,
+                    Err(err) => {
+                        println!("{} {}", format!("Failed to start {}:", module.name).red().bold(), err);
+                        ModuleState::Failed
+                    }
+                };
+                states.lock().unwrap().insert(&module.name, state);
+                state_changed.notify_all();
+            });
         }
+    });
 
-        println!("Successfully started components for compose file: {}", compose_file.display());
+    let states = states.into_inner().unwrap();
+    let failed: Vec<&str> = selected
+        .iter()
+        .filter(|module| states[module.name.as_str()] == ModuleState::Failed)
+        .map(|module| module.name.as_str())
+        .collect();
+    if !failed.is_empty() {
+        println!("Is there a name, resource or port conflict with another module or a previous deployment?");
+        println!("You can also run `nibc-forge clean` to remove any associated docker containers, networks, and/or volumes and try again.");
+        return Err(AppError::DockerCommand(format!("Failed to start modules: {}", failed.join(", "))));
     }
 
     // List all resources created by the deployment
@@ -64,3 +114,83 @@ pub fn handle_start(args: crate::DeploymentArgs) -> Result<(), AppError> {
 
     Ok(())
 }
+
+pub fn handle_restart(args: crate::StartArgs) -> Result<(), AppError> {
+    handle_stop(crate::StopArgs {
+        deployment_dir: args.deployment_dir.clone(),
+        modules: args.modules.clone(),
+    })?;
+    handle_start(args)
+}
+
+fn is_running(compose_files: &[PathBuf], project_name: &str) -> Result<bool, AppError> {
+    let mut ps_command = Command::new("docker");
+    ps_command.arg("compose");
+    for compose_file in compose_files {
+        ps_command.arg("-f").arg(compose_file.to_str().unwrap());
+    }
+    ps_command.arg("--project-name").arg(project_name).arg("ps").arg("-q");
+
+    let ps_output = ps_command.output().map_err(AppError::Io)?;
+    Ok(!ps_output.stdout.is_empty())
+}
+
+// Start the services of a module and wait until it passes its readiness check
+fn start_module(deployment: &Deployment, module: &DeployedModule, timeout: Duration) -> Result<(), AppError> {
+    if module.services.is_empty() {
+        return Ok(());
+    }
+    let compose_file = deployment.compose_file(module);
+    println!("Using compose file: {}", compose_file.display());
+
+    // We start each compose file in a separate command to avoid issues with relative paths in the compose files
+    let mut up_command = Command::new("docker");
+    up_command.arg("compose");
+    for file in deployment.module_compose_files(module) {
+        up_command.arg("-f").arg(file.to_str().unwrap());
+    }
+    up_command
+        .arg("--project-name")
+        .arg(&deployment.project)
+        .arg("up")
+        .arg("-d");
+
+    // Suppress stderr output to hide the irrelevant warnings about orphaned containers
+    let status = up_command.stderr(Stdio::null()).status().map_err(AppError::Io)?;
+
+    if !status.success() {
+        return Err(AppError::DockerCommand(
+            format!("docker compose up failed for {}", compose_file.display()),
+        ));
+    }
+
+    println!("Successfully started components for compose file: {}", compose_file.display());
+
+    let start = Instant::now();
+    while !module_ready(module) {
+        if start.elapsed() > timeout {
+            return Err(AppError::Timeout(format!(
+                "{} was not ready after {}s",
+                module.name,
+                timeout.as_secs()
+            )));
+        }
+        thread::sleep(READINESS_INTERVAL);
+    }
+    println!("{} is ready", module.name);
+    Ok(())
+}
+
+/// A chain is ready once it produces blocks; other modules once all their containers are running (and healthy, for
+/// containers with a healthcheck)
+pub fn module_ready(module: &DeployedModule) -> bool {
+    if module.kind.is_chain() {
+        return chain_status(module).latest_height.unwrap_or_default() > 0;
+    }
+    module.services.iter().all(|service| {
+        matches!(
+            container_state(&service.container).as_deref(),
+            Some("running") | Some("running (healthy)")
+        )
+    })
+}
@@ -0,0 +1,195 @@
+use crate::config::ModuleType;
+use crate::deployment::{DeployedModule, Deployment};
+use crate::error::AppError;
+use crate::utils::{container_state, http_get, port_open, print_table, seconds_since};
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+
+/// Container port of the CometBFT RPC of every chain module
+pub const RPC_PORT: u16 = 26657;
+
+#[derive(Serialize)]
+pub struct DeploymentStatus {
+    pub project: String,
+    pub chains: Vec<ChainStatus>,
+    pub hermes: Vec<ServiceStatus>,
+    pub services: Vec<ServiceStatus>,
+}
+
+#[derive(Serialize)]
+pub struct ChainStatus {
+    pub module: String,
+    pub hostname: Option<String>,
+    pub rpc: Option<String>,
+    pub chain_id: Option<String>,
+    pub latest_height: Option<u64>,
+    pub catching_up: Option<bool>,
+    pub seconds_since_last_block: Option<i64>,
+    /// Why the RPC could not be queried
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ServiceStatus {
+    pub module: String,
+    pub service: String,
+    pub container: String,
+    /// None if the container does not exist
+    pub state: Option<String>,
+    pub endpoints: Vec<EndpointStatus>,
+}
+
+#[derive(Serialize)]
+pub struct EndpointStatus {
+    pub url: String,
+    /// Whether the port accepts connections; not every service speaks HTTP, eg. Postgres
+    pub answering: bool,
+}
+
+pub fn handle_status(args: crate::StatusArgs) -> Result<(), AppError> {
+    let deployment = Deployment::load(Path::new(&args.deployment_dir))?;
+    let status = deployment_status(&deployment);
+
+    if args.json {
+        let output = serde_json::to_string_pretty(&status)
+            .map_err(|err| AppError::InvalidConfig(format!("Failed to serialize status: {}", err)))?;
+        println!("{}", output);
+        return Ok(());
+    }
+
+    println!("Status of deployment in directory: {} (project {})", args.deployment_dir, status.project);
+
+    println!("\n{}", "Chains:".yellow().bold());
+    let rows: Vec<Vec<String>> = status
+        .chains
+        .iter()
+        .map(|chain| {
+            let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+            vec![
+                chain.module.clone(),
+                or_dash(chain.chain_id.clone()),
+                or_dash(chain.latest_height.map(|height| height.to_string())),
+                or_dash(chain.catching_up.map(|catching_up| if catching_up { "yes" } else { "no" }.to_string())),
+                or_dash(chain.seconds_since_last_block.map(|seconds| format!("{}s ago", seconds))),
+                or_dash(chain.rpc.clone()),
+                chain.error.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_table(&["MODULE", "CHAIN ID", "HEIGHT", "CATCHING UP", "LAST BLOCK", "RPC", "ERROR"], &rows);
+
+    for (title, services) in [("Hermes:", &status.hermes), ("Other services:", &status.services)] {
+        if services.is_empty() {
+            continue;
+        }
+        println!("\n{}", title.yellow().bold());
+        let rows: Vec<Vec<String>> = services
+            .iter()
+            .map(|service| {
+                let endpoints: Vec<String> = service
+                    .endpoints
+                    .iter()
+                    .map(|endpoint| {
+                        let answer = if endpoint.answering { "answering" } else { "no answer" };
+                        format!("{} ({})", endpoint.url, answer)
+                    })
+                    .collect();
+                vec![
+                    service.module.clone(),
+                    service.container.clone(),
+                    service.state.clone().unwrap_or_else(|| "not created".to_string()),
+                    endpoints.join(", "),
+                ]
+            })
+            .collect();
+        print_table(&["MODULE", "CONTAINER", "STATE", "ENDPOINTS"], &rows);
+    }
+
+    Ok(())
+}
+
+pub fn deployment_status(deployment: &Deployment) -> DeploymentStatus {
+    let mut status = DeploymentStatus {
+        project: deployment.project.clone(),
+        chains: Vec::new(),
+        hermes: Vec::new(),
+        services: Vec::new(),
+    };
+
+    for module in &deployment.modules {
+        match module.kind {
+            kind if kind.is_chain() => status.chains.push(chain_status(module)),
+            ModuleType::Hermes => status.hermes.extend(service_statuses(module)),
+            _ => status.services.extend(service_statuses(module)),
+        }
+    }
+
+    status
+}
+
+/// Query the CometBFT `/status` endpoint of a chain through its published RPC port
+pub fn chain_status(module: &DeployedModule) -> ChainStatus {
+    let mut status = ChainStatus {
+        module: module.name.clone(),
+        hostname: module.hostname.clone(),
+        rpc: None,
+        chain_id: None,
+        latest_height: None,
+        catching_up: None,
+        seconds_since_last_block: None,
+        error: None,
+    };
+
+    let Some(port) = module.published_port(RPC_PORT) else {
+        status.error = Some("RPC port is not published".to_string());
+        return status;
+    };
+    status.rpc = Some(format!("http://127.0.0.1:{}", port));
+
+    let body = match http_get(port, "/status") {
+        Ok((200, body)) => body,
+        Ok((code, _)) => {
+            status.error = Some(format!("RPC returned HTTP {}", code));
+            return status;
+        }
+        Err(err) => {
+            status.error = Some(err);
+            return status;
+        }
+    };
+    let Ok(response) = serde_json::from_str::<Value>(&body) else {
+        status.error = Some("invalid RPC response".to_string());
+        return status;
+    };
+
+    let string_at = |pointer: &str| response.pointer(pointer).and_then(Value::as_str);
+    status.chain_id = string_at("/result/node_info/network").map(String::from);
+    status.latest_height = string_at("/result/sync_info/latest_block_height").and_then(|height| height.parse().ok());
+    status.catching_up = response.pointer("/result/sync_info/catching_up").and_then(Value::as_bool);
+    status.seconds_since_last_block = string_at("/result/sync_info/latest_block_time").and_then(seconds_since);
+    status
+}
+
+// The container state of each service of a module, and whether its published ports answer HTTP requests
+fn service_statuses(module: &DeployedModule) -> Vec<ServiceStatus> {
+    module
+        .services
+        .iter()
+        .map(|service| ServiceStatus {
+            module: module.name.clone(),
+            service: service.name.clone(),
+            container: service.container.clone(),
+            state: container_state(&service.container),
+            endpoints: service
+                .ports
+                .values()
+                .map(|port| EndpointStatus {
+                    url: format!("http://127.0.0.1:{}", port),
+                    answering: port_open(*port),
+                })
+                .collect(),
+        })
+        .collect()
+}
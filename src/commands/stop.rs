@@ -1,16 +1,24 @@
 use crate::error::AppError;
-use crate::utils::deployment_compose_files;
-use std::path::Path;
+use crate::deployment::Deployment;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-pub fn handle_stop(args: crate::DeploymentArgs) -> Result<(), AppError> {
+pub fn handle_stop(args: crate::StopArgs) -> Result<(), AppError> {
     println!("Stopping deployment in directory: {}", args.deployment_dir);
 
-    let deployment_dir = Path::new(&args.deployment_dir);
-    let project_name = deployment_dir.file_name().unwrap().to_str().unwrap();
-
-    // Collect all docker-compose.yml files from subdirectories
-    let compose_files = deployment_compose_files(deployment_dir)?;
+    let deployment = Deployment::load(Path::new(&args.deployment_dir))?;
+    let project_name = deployment.project.as_str();
+    deployment.check_project()?;
+    let compose_files: Vec<PathBuf> = deployment
+        .select_modules(&args.modules)?
+        .into_iter()
+        .filter(|module| !module.services.is_empty())
+        .flat_map(|module| deployment.module_compose_files(module))
+        .collect();
+    if compose_files.is_empty() {
+        println!("Nothing to stop; the selected modules have no services");
+        return Ok(());
+    }
 
     // Stop running containers associated with this deployment
     let mut stop_command = Command::new("docker");
@@ -0,0 +1,93 @@
+use crate::commands::clean::handle_clean;
+use crate::commands::create::handle_create;
+use crate::commands::endpoints::module_endpoints;
+use crate::commands::start::handle_start;
+use crate::commands::stop::handle_stop;
+use crate::commands::wait::wait_until_ready;
+use crate::deployment::Deployment;
+use crate::error::AppError;
+use colored::Colorize;
+use serde_json::json;
+use std::env;
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+pub fn handle_up(args: crate::UpArgs) -> Result<(), AppError> {
+    // Without a deployment dir, the deployment is created in a new directory under the system temp dir
+    let deployment_dir = args.deployment_dir.unwrap_or_else(|| {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.subsec_nanos()).unwrap_or_default();
+        env::temp_dir()
+            .join(format!("nibc-forge-{}-{:08x}", std::process::id(), nanos))
+            .to_string_lossy()
+            .into_owned()
+    });
+
+    // Nothing is left behind when create fails, so there is nothing to clean up either
+    handle_create(crate::CreateArgs {
+        config_file: args.config_file,
+        deployment_dir: deployment_dir.clone(),
+        force: false,
+    })?;
+
+    // The timeout covers both starting the modules and waiting for the channels
+    let deadline = Instant::now() + args.timeout;
+    let result = handle_start(crate::StartArgs {
+        deployment_dir: deployment_dir.clone(),
+        modules: Vec::new(),
+        timeout: args.timeout,
+    })
+    .and_then(|_| {
+        let deployment = Deployment::load(Path::new(&deployment_dir))?;
+        wait_until_ready(&deployment, deadline.saturating_duration_since(Instant::now()), args.interval)?;
+        Ok(deployment)
+    });
+
+    let deployment = match result {
+        Ok(deployment) => deployment,
+        Err(err) => {
+            if args.keep_on_failure {
+                println!(
+                    "{} the deployment was kept for inspection in {}; remove it with `nibc-forge down --deployment-dir {}`",
+                    "Failed to bring up the deployment:".red().bold(),
+                    deployment_dir,
+                    deployment_dir
+                );
+            } else {
+                println!("{}", "Failed to bring up the deployment; removing it".red().bold());
+                if let Err(down_err) = down(&deployment_dir) {
+                    println!("Failed to remove the deployment in {}: {}", deployment_dir, down_err);
+                }
+            }
+            return Err(err);
+        }
+    };
+
+    // The endpoints are printed last, so scripts can take the JSON from the end of the output. They are the same as
+    // those printed by the endpoints command
+    let output = json!({
+        "deployment_dir": deployment_dir,
+        "project": deployment.project,
+        "endpoints": module_endpoints(&deployment),
+    });
+    println!("{}", serde_json::to_string_pretty(&output).unwrap());
+    Ok(())
+}
+
+pub fn handle_down(args: crate::DeploymentArgs) -> Result<(), AppError> {
+    down(&args.deployment_dir)
+}
+
+// Stop the deployment, then remove its resources and directory without asking for confirmation
+fn down(deployment_dir: &str) -> Result<(), AppError> {
+    if let Err(err) = handle_stop(crate::StopArgs {
+        deployment_dir: deployment_dir.to_string(),
+        modules: Vec::new(),
+    }) {
+        println!("Failed to stop the deployment, removing it anyway: {}", err);
+    }
+    handle_clean(crate::CleanArgs {
+        deployment_dir: deployment_dir.to_string(),
+        yes: true,
+        purge: true,
+    })
+}
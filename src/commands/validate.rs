@@ -0,0 +1,86 @@
+use crate::commands::create::{parse_config, validate_config, ModuleInstance};
+use crate::config::resolve_spec;
+use crate::error::AppError;
+use colored::Colorize;
+use std::path::Path;
+use std::process::Command;
+
+pub fn handle_validate(args: crate::ValidateArgs) -> Result<(), AppError> {
+    println!("Validating config file: {}", args.config_file);
+
+    // Print the spec as create will see it, with its `extends` and `include` keys resolved
+    let spec = resolve_spec(Path::new(&args.config_file))?;
+    let resolved = toml::to_string_pretty(&spec)
+        .map_err(|err| AppError::InvalidConfig(format!("Failed to serialize resolved config: {}", err)))?;
+    println!("{}", resolved);
+
+    let config = parse_config(spec)?;
+    let mut validation = validate_config(&config)?;
+
+    // Let docker compose check each module's compose file with the env from the config applied
+    if compose_available() {
+        for instance in &validation.instances {
+            if let Err(err) = check_compose_config(instance) {
+                validation.errors.push(err.to_string());
+            }
+        }
+    } else {
+        validation.warnings.push("docker compose is not available; skipping the compose file checks".to_string());
+    }
+
+    for warning in &validation.warnings {
+        println!("{} {}", "WARNING: ".yellow().bold(), warning);
+    }
+    for error in &validation.errors {
+        println!("{} {}", "ERROR: ".red().bold(), error);
+    }
+
+    if !validation.errors.is_empty() {
+        return Err(AppError::InvalidConfig(format!(
+            "{} validation error(s) found in {}",
+            validation.errors.len(),
+            args.config_file
+        )));
+    }
+
+    println!("{}", format!("{} is valid", args.config_file).green().bold());
+    Ok(())
+}
+
+fn compose_available() -> bool {
+    Command::new("docker")
+        .arg("compose")
+        .arg("version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+fn check_compose_config(instance: &ModuleInstance) -> Result<(), AppError> {
+    let compose_file = Path::new(&instance.module.module_dir).join("docker-compose.yml");
+    if !compose_file.exists() {
+        return Ok(());
+    }
+
+    // The env is passed to the process rather than written to a .env file, so nothing is written to disk
+    let output = Command::new("docker")
+        .arg("compose")
+        .arg("-f")
+        .arg(compose_file.to_str().unwrap())
+        .arg("--project-name")
+        .arg("nibc-forge-validate")
+        .arg("config")
+        .arg("--quiet")
+        .envs(instance.env.iter().map(|(name, value)| (name, value)))
+        .output()
+        .map_err(AppError::Io)?;
+
+    if !output.status.success() {
+        return Err(AppError::DockerCommand(format!(
+            "docker compose config failed for {}: {}",
+            instance.dir_name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use crate::commands::ibc_channels::{channel_results, expected_links};
+use crate::commands::status::chain_status;
+use crate::deployment::Deployment;
+use crate::error::AppError;
+use colored::Colorize;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub fn handle_wait(args: crate::WaitArgs) -> Result<(), AppError> {
+    println!(
+        "Waiting up to {}s for the chains and IBC channels of deployment in directory: {}",
+        args.timeout.as_secs(),
+        args.deployment_dir
+    );
+
+    let deployment = Deployment::load(Path::new(&args.deployment_dir))?;
+    wait_until_ready(&deployment, args.timeout, args.interval)?;
+
+    println!("{}", "Deployment is ready".green().bold());
+    Ok(())
+}
+
+/// Poll the deployment until every chain is producing blocks and every expected IBC channel has been created. Fails
+/// with AppError::ChannelFailed as soon as a channel fails or is skipped, and with AppError::Timeout when time runs out
+pub fn wait_until_ready(deployment: &Deployment, timeout: Duration, interval: Duration) -> Result<(), AppError> {
+    let start = Instant::now();
+    let chain_modules: Vec<_> = deployment.modules.iter().filter(|module| module.kind.is_chain()).collect();
+    loop {
+        // The chain ids of the links are only known once their chains answer
+        let links = match deployment.hermes_module() {
+            Some(_) => expected_links(deployment)?,
+            None => Vec::new(),
+        };
+
+        let ready_chains: Vec<&str> = chain_modules
+            .iter()
+            .filter(|module| {
+                let status = chain_status(module);
+                status.latest_height.unwrap_or_default() > 0 && status.catching_up == Some(false)
+            })
+            .map(|module| module.name.as_str())
+            .collect();
+
+        // The Hermes container may not be ready to answer yet; its channels then count as pending
+        let results = match deployment.hermes_container() {
+            Ok(container) if !links.is_empty() => channel_results(container).unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
+        let mut created = 0;
+        for link in &links {
+            let Some(result) = results.iter().find(|result| link.matches(result)) else {
+                continue;
+            };
+            if result.status != "success" {
+                return Err(AppError::ChannelFailed(format!(
+                    "channel between {}:{} and {}:{} {}: {}",
+                    link.a_hostname,
+                    link.a_port,
+                    link.b_hostname,
+                    link.b_port,
+                    if result.status == "skipped" { "was skipped" } else { "failed" },
+                    result.error.as_deref().unwrap_or("unknown error")
+                )));
+            }
+            created += 1;
+        }
+
+        let elapsed = start.elapsed();
+        println!(
+            "[{:>4}s] chains producing blocks: {}/{}, IBC channels created: {}/{}",
+            elapsed.as_secs(),
+            ready_chains.len(),
+            chain_modules.len(),
+            created,
+            links.len()
+        );
+        if ready_chains.len() == chain_modules.len() && created == links.len() {
+            return Ok(());
+        }
+
+        if elapsed + interval > timeout {
+            let pending_chains: Vec<&str> = chain_modules
+                .iter()
+                .map(|module| module.name.as_str())
+                .filter(|name| !ready_chains.contains(name))
+                .collect();
+            return Err(AppError::Timeout(format!(
+                "after {}s; chains not producing blocks: [{}], IBC channels still pending: {}",
+                elapsed.as_secs(),
+                pending_chains.join(", "),
+                links.len() - created
+            )));
+        }
+        thread::sleep(interval);
+    }
+}
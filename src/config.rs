@@ -1,17 +1,429 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::chains::{gaia::Gaia, namada::Namada, osmosis::Osmosis, ChainType};
+use crate::compose::resolve_references;
+use crate::error::AppError;
+use crate::utils::HERMES_TEMPLATE_DIR;
+
+/// The kind of a module; unknown kinds are rejected when the config is parsed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModuleType {
+    Namada,
+    Gaia,
+    Osmosis,
+    Hermes,
+    Aux,
+}
+
+impl ModuleType {
+    /// The chain behaviour of this module, or None if it is not a chain
+    pub fn chain_type(&self) -> Option<&'static dyn ChainType> {
+        match self {
+            ModuleType::Namada => Some(&Namada),
+            ModuleType::Gaia => Some(&Gaia),
+            ModuleType::Osmosis => Some(&Osmosis),
+            ModuleType::Hermes | ModuleType::Aux => None,
+        }
+    }
+
+    pub fn is_chain(&self) -> bool {
+        self.chain_type().is_some()
+    }
+}
 
 #[derive(Deserialize)]
 pub struct ModuleConfig {
+    /// Identifies the module when merging a spec with the specs it extends or includes
+    pub name: Option<String>,
     pub module_dir: String,
     #[serde(rename = "type")]
-    pub _type: String,
+    pub kind: ModuleType,
     pub rpc_hostname: Option<String>,
     pub relayer_key: Option<String>,
     pub hermes_template: Option<String>,
-    pub docker_env: Option<String>,
+    pub docker_env: Option<DockerEnv>,
+    pub genesis_state: Option<String>,
+    /// Modules (by name) that must be ready before this module is started, instead of the default order of chains,
+    /// then Hermes, then everything else
+    pub depends_on: Option<Vec<String>>,
+}
+
+/// Variables for a module's .env file, given either as a comma separated `NAME=value` list or as a table
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum DockerEnv {
+    List(String),
+    Table(BTreeMap<String, EnvValue>),
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum EnvValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+}
+
+impl fmt::Display for EnvValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnvValue::String(value) => write!(f, "{}", value),
+            EnvValue::Integer(value) => write!(f, "{}", value),
+            EnvValue::Float(value) => write!(f, "{}", value),
+            EnvValue::Boolean(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl DockerEnv {
+    pub fn entries(&self) -> Vec<(String, String)> {
+        match self {
+            DockerEnv::List(list) => list
+                .split(',')
+                .filter(|entry| !entry.trim().is_empty())
+                .map(|entry| match entry.split_once('=') {
+                    Some((name, value)) => (name.trim().to_string(), value.to_string()),
+                    None => (entry.trim().to_string(), String::new()),
+                })
+                .collect(),
+            DockerEnv::Table(table) => table
+                .iter()
+                .map(|(name, value)| (name.clone(), value.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl ModuleConfig {
+    /// The Hermes template of this module: the one given in the config, or else the default for its type
+    pub fn hermes_template(&self) -> Option<PathBuf> {
+        if let Some(path) = &self.hermes_template {
+            return Some(PathBuf::from(path));
+        }
+        match self.kind {
+            ModuleType::Hermes => Some(Path::new(HERMES_TEMPLATE_DIR).join("hermes.toml")),
+            _ => self.kind.chain_type().map(|chain| chain.default_hermes_template()),
+        }
+    }
+}
+
+/// An IBC channel to be created by Hermes between two chains, identified by their `rpc_hostname`
+#[derive(Clone, Deserialize)]
+pub struct LinkConfig {
+    pub a_chain: String,
+    pub b_chain: String,
+    #[serde(default = "default_port")]
+    pub a_port: String,
+    #[serde(default = "default_port")]
+    pub b_port: String,
+    #[serde(default = "default_ordering")]
+    pub ordering: String,
+    pub version: Option<String>,
+}
+
+fn default_port() -> String {
+    "transfer".to_string()
+}
+
+fn default_ordering() -> String {
+    "unordered".to_string()
 }
 
 #[derive(Deserialize)]
 pub struct Config {
+    /// The docker compose project name to create the deployment under, instead of a generated one
+    pub name: Option<String>,
     pub modules: Vec<ModuleConfig>,
-}
\ No newline at end of file
+    /// `rpc_hostname` of the chain to connect to every other chain when no links are given
+    pub hub: Option<String>,
+    #[serde(default)]
+    pub links: Vec<LinkConfig>,
+    /// Pick free host ports for every `${*_PORT}` variable not set in a module's docker_env
+    #[serde(default)]
+    pub auto_ports: bool,
+    /// Variables added to the .env file of every module
+    #[serde(default)]
+    pub env: BTreeMap<String, EnvValue>,
+}
+
+impl Config {
+    /// Variables for a module's .env file: the global `[env]` table, overridden by the module's `docker_env`, followed by
+    /// HOSTNAME when `rpc_hostname` is set. `${VAR}` references between the variables are resolved
+    pub fn module_env(&self, module: &ModuleConfig) -> Result<Vec<(String, String)>, AppError> {
+        let mut env: Vec<(String, String)> = self
+            .env
+            .iter()
+            .map(|(name, value)| (name.clone(), value.to_string()))
+            .collect();
+        let module_entries = module.docker_env.as_ref().map(DockerEnv::entries).unwrap_or_default();
+        let hostname_entry = module.rpc_hostname.iter().map(|hostname| ("HOSTNAME".to_string(), hostname.clone()));
+        for (name, value) in module_entries.into_iter().chain(hostname_entry) {
+            env.retain(|(existing, _)| existing != &name);
+            env.push((name, value));
+        }
+
+        resolve_references(&mut env).map_err(|err| {
+            AppError::InvalidConfig(format!("Invalid env for module {}: {}", module.module_dir, err))
+        })?;
+        Ok(env)
+    }
+}
+
+/// Read a spec and merge in the specs named by its `extends` and `include` keys. The extended spec is applied first,
+/// then the included specs in order, then the spec itself. Modules with the same `name` are merged field by field, and
+/// a module with `remove = true` removes the module of that name. Other tables are merged, and any other value
+/// replaces the one from the earlier spec
+pub fn resolve_spec(path: &Path) -> Result<toml::Table, AppError> {
+    resolve_spec_file(path, &mut Vec::new())
+}
+
+fn resolve_spec_file(path: &Path, parents: &mut Vec<PathBuf>) -> Result<toml::Table, AppError> {
+    let canonical = path.canonicalize().map_err(|err| {
+        AppError::InvalidConfig(format!("Failed to read config file {}: {}", path.display(), err))
+    })?;
+    if parents.contains(&canonical) {
+        return Err(AppError::InvalidConfig(format!(
+            "Config file {} extends or includes itself",
+            path.display()
+        )));
+    }
+
+    let content = fs::read_to_string(path).map_err(|err| {
+        AppError::InvalidConfig(format!("Failed to read config file {}: {}", path.display(), err))
+    })?;
+    let mut spec: toml::Table = toml::from_str(&content).map_err(|err| {
+        AppError::InvalidConfig(format!("Invalid config file format in {}: {}", path.display(), err))
+    })?;
+
+    // Paths of extended and included specs are relative to the spec that names them
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let mut bases = Vec::new();
+    match spec.remove("extends") {
+        Some(toml::Value::String(parent)) => bases.push(base_dir.join(parent)),
+        Some(_) => {
+            return Err(AppError::InvalidConfig(format!(
+                "`extends` must be a path in {}",
+                path.display()
+            )))
+        }
+        None => {}
+    }
+    match spec.remove("include") {
+        Some(toml::Value::String(include)) => bases.push(base_dir.join(include)),
+        Some(toml::Value::Array(includes)) => {
+            for include in includes {
+                match include {
+                    toml::Value::String(include) => bases.push(base_dir.join(include)),
+                    _ => {
+                        return Err(AppError::InvalidConfig(format!(
+                            "`include` must be a list of paths in {}",
+                            path.display()
+                        )))
+                    }
+                }
+            }
+        }
+        Some(_) => {
+            return Err(AppError::InvalidConfig(format!(
+                "`include` must be a list of paths in {}",
+                path.display()
+            )))
+        }
+        None => {}
+    }
+
+    parents.push(canonical);
+    let mut resolved = toml::Table::new();
+    for base in bases {
+        let base_spec = resolve_spec_file(&base, parents)?;
+        merge_spec(&mut resolved, base_spec)?;
+    }
+    parents.pop();
+
+    merge_spec(&mut resolved, spec)?;
+    Ok(resolved)
+}
+
+fn merge_spec(resolved: &mut toml::Table, spec: toml::Table) -> Result<(), AppError> {
+    for (key, value) in spec {
+        if key == "modules" {
+            let modules = match value {
+                toml::Value::Array(modules) => modules,
+                _ => return Err(AppError::InvalidConfig("`modules` must be a list of tables".to_string())),
+            };
+            let mut merged = match resolved.remove("modules") {
+                Some(toml::Value::Array(existing)) => existing,
+                _ => Vec::new(),
+            };
+            let mut names = Vec::new();
+            for module in modules {
+                if let Some(name) = module.get("name").and_then(toml::Value::as_str) {
+                    if names.contains(&name.to_string()) {
+                        return Err(AppError::InvalidConfig(format!("Module name {} is used more than once", name)));
+                    }
+                    names.push(name.to_string());
+                }
+                merge_module(&mut merged, module)?;
+            }
+            resolved.insert(key, toml::Value::Array(merged));
+        } else {
+            merge_value(resolved, key, value);
+        }
+    }
+    Ok(())
+}
+
+fn merge_module(modules: &mut Vec<toml::Value>, module: toml::Value) -> Result<(), AppError> {
+    let mut module = match module {
+        toml::Value::Table(module) => module,
+        _ => return Err(AppError::InvalidConfig("`modules` must be a list of tables".to_string())),
+    };
+    let name = match module.get("name") {
+        Some(toml::Value::String(name)) => Some(name.clone()),
+        _ => None,
+    };
+    let remove = matches!(module.remove("remove"), Some(toml::Value::Boolean(true)));
+
+    let existing = name.as_ref().and_then(|name| {
+        modules
+            .iter()
+            .position(|existing| existing.get("name").and_then(toml::Value::as_str) == Some(name))
+    });
+    match (existing, remove) {
+        (Some(index), true) => {
+            modules.remove(index);
+        }
+        (None, true) => {
+            return Err(AppError::InvalidConfig(format!(
+                "Cannot remove module {}: no module of that name in the extended or included specs",
+                name.as_deref().unwrap_or("without a name")
+            )))
+        }
+        (Some(index), false) => {
+            if let toml::Value::Table(existing) = &mut modules[index] {
+                for (key, value) in module {
+                    merge_value(existing, key, value);
+                }
+            }
+        }
+        (None, false) => modules.push(toml::Value::Table(module)),
+    }
+    Ok(())
+}
+
+fn merge_value(table: &mut toml::Table, key: String, value: toml::Value) {
+    match (table.get_mut(&key), value) {
+        (Some(toml::Value::Table(existing)), toml::Value::Table(value)) => {
+            for (key, value) in value {
+                merge_value(existing, key, value);
+            }
+        }
+        (_, value) => {
+            table.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merged(base: &str, spec: &str) -> Result<toml::Table, AppError> {
+        let mut resolved = toml::Table::new();
+        merge_spec(&mut resolved, toml::from_str(base).unwrap())?;
+        merge_spec(&mut resolved, toml::from_str(spec).unwrap())?;
+        Ok(resolved)
+    }
+
+    fn module_names(spec: &toml::Table) -> Vec<&str> {
+        spec["modules"].as_array().unwrap().iter().filter_map(|module| module["name"].as_str()).collect()
+    }
+
+    const BASE: &str = r#"
+name = "base"
+
+[env]
+CHAIN_ID = "local"
+DENOM = "stake"
+
+[[modules]]
+name = "namada"
+kind = "namada"
+
+[[modules]]
+name = "gaia"
+kind = "gaia"
+[modules.env]
+RPC_PORT = "27657"
+REST_PORT = "1318"
+
+[[modules]]
+name = "hermes"
+kind = "hermes"
+"#;
+
+    #[test]
+    fn merge_spec_merges_modules_by_name() {
+        let spec = merged(
+            BASE,
+            r#"
+[env]
+DENOM = "uatom"
+
+[[modules]]
+name = "gaia"
+[modules.env]
+RPC_PORT = "28657"
+
+[[modules]]
+name = "osmosis"
+kind = "osmosis"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(spec["name"].as_str(), Some("base"));
+        assert_eq!(spec["env"]["CHAIN_ID"].as_str(), Some("local"));
+        assert_eq!(spec["env"]["DENOM"].as_str(), Some("uatom"));
+        assert_eq!(module_names(&spec), vec!["namada", "gaia", "hermes", "osmosis"]);
+        let gaia = &spec["modules"][1];
+        assert_eq!(gaia["kind"].as_str(), Some("gaia"));
+        assert_eq!(gaia["env"]["RPC_PORT"].as_str(), Some("28657"));
+        assert_eq!(gaia["env"]["REST_PORT"].as_str(), Some("1318"));
+    }
+
+    #[test]
+    fn merge_spec_removes_modules_by_name() {
+        let spec = merged(BASE, "[[modules]]\nname = \"gaia\"\nremove = true\n").unwrap();
+        assert_eq!(module_names(&spec), vec!["namada", "hermes"]);
+
+        let err = merged(BASE, "[[modules]]\nname = \"osmosis\"\nremove = true\n").unwrap_err();
+        assert!(matches!(err, AppError::InvalidConfig(message) if message.contains("Cannot remove module osmosis")));
+    }
+
+    #[test]
+    fn merge_spec_rejects_duplicate_names_in_one_spec() {
+        let err = merged(BASE, "[[modules]]\nname = \"gaia2\"\nkind = \"gaia\"\n\n[[modules]]\nname = \"gaia2\"\n")
+            .unwrap_err();
+        assert!(matches!(err, AppError::InvalidConfig(message) if message.contains("gaia2 is used more than once")));
+    }
+
+    #[test]
+    fn resolve_spec_rejects_circular_extends() {
+        let dir = std::env::temp_dir().join(format!("nibc-forge-spec-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.toml"), "extends = \"b.toml\"\n").unwrap();
+        fs::write(dir.join("b.toml"), "include = [\"a.toml\"]\n").unwrap();
+        let result = resolve_spec(&dir.join("a.toml"));
+        fs::remove_dir_all(&dir).unwrap();
+        let Err(AppError::InvalidConfig(message)) = result else {
+            panic!("circular extends accepted");
+        };
+        assert!(message.contains("extends or includes itself"));
+    }
+}
@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+use crate::compose::parse_compose;
+use crate::config::ModuleType;
+use crate::error::AppError;
+
+/// Name of the manifest `create` writes to the deployment directory
+pub const MANIFEST_FILE: &str = "nibc-forge.lock.toml";
+
+/// Compose file that `restore` writes next to a module's compose file, running its services from snapshot images
+pub const SNAPSHOT_COMPOSE_FILE: &str = "docker-compose.snapshot.yml";
+/// Compose file written by `create` next to each module's compose file, labelling the resources it creates
+pub const LABELS_COMPOSE_FILE: &str = "docker-compose.override.yml";
+
+/// Label added to every container, network and volume of a deployment, holding its project name
+pub const DEPLOYMENT_LABEL: &str = "nibc-forge.deployment";
+/// Label holding the absolute path of the deployment directory, so resources can be found after it is deleted
+pub const DIR_LABEL: &str = "nibc-forge.dir";
+
+/// Record of a created deployment, read by every command that operates on it
+#[derive(Deserialize, Serialize)]
+pub struct Deployment {
+    /// The docker compose project name the deployment's resources are created under
+    pub project: String,
+    pub modules: Vec<DeployedModule>,
+    /// The spec the deployment was created from, with its `extends` and `include` keys resolved
+    pub spec: toml::Table,
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DeployedModule {
+    /// The module's `name` in the spec, or its directory name if it has none
+    pub name: String,
+    /// Directory of the module within the deployment
+    pub dir: String,
+    #[serde(rename = "type")]
+    pub kind: ModuleType,
+    pub hostname: Option<String>,
+    /// Modules that must be ready before this one is started, when given in the spec
+    pub depends_on: Option<Vec<String>>,
+    /// Host ports of the module's `${*_PORT}` variables
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub ports: BTreeMap<String, u16>,
+    #[serde(default)]
+    pub services: Vec<DeployedService>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DeployedService {
+    pub name: String,
+    pub container: String,
+    /// Host ports published by the service, keyed by container port
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub ports: BTreeMap<String, u16>,
+}
+
+impl DeployedModule {
+    /// The host port a container port of one of the module's services is published on
+    pub fn published_port(&self, container_port: u16) -> Option<u16> {
+        self.services
+            .iter()
+            .find_map(|service| service.ports.get(&container_port.to_string()).copied())
+    }
+}
+
+impl Deployment {
+    pub fn load(dir: &Path) -> Result<Deployment, AppError> {
+        let manifest_path = dir.join(MANIFEST_FILE);
+        if !manifest_path.exists() {
+            return Err(AppError::InvalidConfig(format!(
+                "No {} found in {}; is this a deployment created by `nibc-forge create`?",
+                MANIFEST_FILE,
+                dir.display()
+            )));
+        }
+
+        let content = fs::read_to_string(&manifest_path).map_err(AppError::Io)?;
+        let mut deployment: Deployment = toml::from_str(&content).map_err(|err| {
+            AppError::InvalidConfig(format!("Invalid deployment manifest {}: {}", manifest_path.display(), err))
+        })?;
+        deployment.dir = dir.to_path_buf();
+        Ok(deployment)
+    }
+
+    pub fn write(&self) -> Result<(), AppError> {
+        let content = toml::to_string_pretty(self)
+            .map_err(|err| AppError::InvalidConfig(format!("Failed to serialize deployment manifest: {}", err)))?;
+        fs::write(self.dir.join(MANIFEST_FILE), content).map_err(AppError::Io)
+    }
+
+    /// The compose files of the modules that have services, in the order of the spec
+    pub fn compose_files(&self) -> Vec<PathBuf> {
+        self.modules
+            .iter()
+            .filter(|module| !module.services.is_empty())
+            .flat_map(|module| self.module_compose_files(module))
+            .collect()
+    }
+
+    /// The modules with the given names, or every module if no names are given
+    pub fn select_modules(&self, names: &[String]) -> Result<Vec<&DeployedModule>, AppError> {
+        if names.is_empty() {
+            return Ok(self.modules.iter().collect());
+        }
+        names
+            .iter()
+            .map(|name| {
+                self.module(name).ok_or_else(|| {
+                    let known: Vec<&str> = self.modules.iter().map(|module| module.name.as_str()).collect();
+                    AppError::InvalidConfig(format!(
+                        "No module {} in the deployment; its modules are: {}",
+                        name,
+                        known.join(", ")
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    pub fn compose_file(&self, module: &DeployedModule) -> PathBuf {
+        self.dir.join(&module.dir).join("docker-compose.yml")
+    }
+
+    /// The compose files to run a module with: its own, followed by the labels override and by the snapshot override
+    /// if it was restored from one
+    pub fn module_compose_files(&self, module: &DeployedModule) -> Vec<PathBuf> {
+        let module_dir = self.dir.join(&module.dir);
+        let mut files = vec![self.compose_file(module)];
+        for file in [LABELS_COMPOSE_FILE, SNAPSHOT_COMPOSE_FILE] {
+            if module_dir.join(file).exists() {
+                files.push(module_dir.join(file));
+            }
+        }
+        files
+    }
+
+    /// Keys and docker names of the named volumes a module's compose file creates. The docker name is the volume's
+    /// `name`, or else the key prefixed with the project name
+    pub fn module_volumes(&self, module: &DeployedModule) -> Result<Vec<(String, String)>, AppError> {
+        if module.services.is_empty() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(self.compose_file(module)).map_err(AppError::Io)?;
+        let compose = parse_compose(&content)?;
+        let Some(volumes) = compose.get("volumes").and_then(Value::as_mapping) else {
+            return Ok(Vec::new());
+        };
+        Ok(volumes
+            .iter()
+            .filter(|(_, volume)| volume.get("external").is_none())
+            .filter_map(|(key, volume)| {
+                let key = key.as_str()?;
+                let name = match volume.get("name").and_then(Value::as_str) {
+                    Some(name) => name.to_string(),
+                    None => format!("{}_{}", self.project, key),
+                };
+                Some((key.to_string(), name))
+            })
+            .collect())
+    }
+
+    /// Find a module by its name or directory
+    pub fn module(&self, name: &str) -> Option<&DeployedModule> {
+        self.modules
+            .iter()
+            .find(|module| module.name == name)
+            .or_else(|| self.modules.iter().find(|module| module.dir == name))
+    }
+
+    /// Check that the containers of the deployment's compose project were created from this deployment directory, so
+    /// that commands never act on another deployment that happens to use the same project name
+    pub fn check_project(&self) -> Result<(), AppError> {
+        let output = Command::new("docker")
+            .arg("container")
+            .arg("ls")
+            .arg("--all")
+            .arg("--filter")
+            .arg(format!("label=com.docker.compose.project={}", self.project))
+            .arg("--format")
+            .arg("{{.Names}}\t{{.Label \"com.docker.compose.project.config_files\"}}")
+            .output()
+            .map_err(AppError::Io)?;
+        if !output.status.success() {
+            return Err(AppError::DockerCommand(format!(
+                "Failed to list the containers of project {}: {}",
+                self.project,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let deployment_dir = self.dir.canonicalize().map_err(AppError::Io)?;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let (container, config_files) = line.split_once('\t').unwrap_or((line, ""));
+            let owned = config_files.split(',').all(|config_file| {
+                let config_file = Path::new(config_file);
+                config_file.canonicalize().unwrap_or(config_file.to_path_buf()).starts_with(&deployment_dir)
+            });
+            if !owned {
+                return Err(AppError::InvalidConfig(format!(
+                    "Container {} of project {} was created from {}, not from the deployment in {}",
+                    container,
+                    self.project,
+                    config_files,
+                    self.dir.display()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The modules that must be ready before a module is started: its `depends_on` modules, or else all chains for
+    /// Hermes, and all chains and Hermes for any other module that is not a chain
+    pub fn dependencies(&self, module: &DeployedModule) -> Vec<&DeployedModule> {
+        if let Some(depends_on) = &module.depends_on {
+            return depends_on.iter().filter_map(|name| self.module(name)).collect();
+        }
+        self.modules
+            .iter()
+            .filter(|other| match module.kind {
+                kind if kind.is_chain() => false,
+                ModuleType::Hermes => other.kind.is_chain(),
+                _ => other.kind.is_chain() || other.kind == ModuleType::Hermes,
+            })
+            .collect()
+    }
+
+    pub fn hermes_module(&self) -> Option<&DeployedModule> {
+        self.modules.iter().find(|module| module.kind == ModuleType::Hermes)
+    }
+
+    /// The container running the Hermes relayer
+    pub fn hermes_container(&self) -> Result<&str, AppError> {
+        self.hermes_module()
+            .and_then(|module| module.services.first())
+            .map(|service| service.container.as_str())
+            .ok_or_else(|| AppError::InvalidConfig("The deployment has no hermes module".to_string()))
+    }
+}
+
+/// A project name for a new deployment: the directory name, made a valid compose project name, followed by a hash of
+/// the directory path and the time of creation so that deployments in directories of the same name do not collide
+pub fn generate_project_id(dir: &Path) -> String {
+    let base: String = dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("deployment")
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    let base = base.trim_start_matches(['-', '_']);
+
+    let mut hasher = DefaultHasher::new();
+    dir.canonicalize().unwrap_or(dir.to_path_buf()).hash(&mut hasher);
+    SystemTime::now().hash(&mut hasher);
+    std::process::id().hash(&mut hasher);
+
+    let hash = format!("{:08x}", hasher.finish() as u32);
+    if base.is_empty() {
+        hash
+    } else {
+        format!("{}-{}", base, hash)
+    }
+}
+
+/// Compose project names may only contain lowercase letters, digits, dashes and underscores, and must start with a
+/// letter or digit
+pub fn valid_project_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
@@ -11,6 +11,24 @@ pub enum AppError {
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
 
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("IBC channel creation failed: {0}")]
+    ChannelFailed(String),
+
     #[error("Unknown error occurred")]
     Unknown,
 }
+
+impl AppError {
+    /// Process exit code for the error, so scripts can tell a timeout or a failed channel from other errors (clap
+    /// already uses 2 for invalid arguments)
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::Timeout(_) => 3,
+            AppError::ChannelFailed(_) => 4,
+            _ => 1,
+        }
+    }
+}
@@ -1,10 +1,14 @@
+pub mod chains;
 pub mod commands;
+pub mod compose;
 pub mod error;
 pub mod config;
+pub mod deployment;
 pub mod utils;
 
-use clap::{Args, Parser, Subcommand};
-use crate::commands::{create::handle_create, start::handle_start, stop::handle_stop, clean::handle_clean, list::handle_list, ibc_channels::handle_ibc_channels, dump_db::handle_dump_db};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::time::Duration;
+use crate::commands::{create::handle_create, start::{handle_start, handle_restart}, stop::handle_stop, clean::handle_clean, list::handle_list, ibc_channels::handle_ibc_channels, dump_db::handle_dump_db, validate::handle_validate, prune::handle_prune, status::handle_status, wait::handle_wait, up::{handle_up, handle_down}, reset::handle_reset, snapshot::{handle_snapshot, handle_restore}, clone::handle_clone, endpoints::handle_endpoints};
 
 #[derive(Parser)]
 #[command(name = "nibc-forge")]
@@ -19,23 +23,60 @@ enum Commands {
     /// Create a new deployment
     Create(CreateArgs),
 
+    /// Check a deployment config for errors without creating the deployment
+    Validate(ValidateArgs),
+
+    /// Create and start a deployment, wait until it is ready and print its endpoints as JSON
+    Up(UpArgs),
+
+    /// Stop a deployment and remove its resources and directory
+    Down(DeploymentArgs),
+
     /// Start an existing deployment
-    Start(DeploymentArgs),
+    Start(StartArgs),
 
     /// Stop a running deployment
-    Stop(DeploymentArgs),
+    Stop(StopArgs),
+
+    /// Stop and start the modules of a deployment again
+    Restart(StartArgs),
+
+    /// Remove the containers and volumes of a deployment's modules, and start them again from a fresh state
+    Reset(ResetArgs),
+
+    /// Stop a deployment and save the state of its containers and volumes in the deployment directory, or delete a
+    /// snapshot
+    Snapshot(SnapshotArgs),
+
+    /// Replace the state of a deployment with a snapshot taken earlier, and start it
+    Restore(RestoreArgs),
+
+    /// Copy a deployment, along with the state of its containers and volumes, to a new directory and project
+    Clone(CloneArgs),
+
+    /// Print the URLs, chain ids, denoms and IBC channels of a deployment's modules, for use by frontends
+    Endpoints(EndpointsArgs),
 
-    /// Clean up deployment data
-    Clean(DeploymentArgs),
+    /// Remove the containers, networks, volumes and snapshot images of a deployment
+    Clean(CleanArgs),
+
+    /// Remove the containers, networks, volumes and snapshot images of deployments whose directory has been deleted
+    Prune(PruneArgs),
+
+    /// Show the health of the chains, Hermes and other services of a deployment
+    Status(StatusArgs),
+
+    /// Wait until the chains of a deployment produce blocks and all its IBC channels are created
+    Wait(WaitArgs),
 
     /// List containers, networks and volumes associated with a deployment
     List(DeploymentArgs),
 
     /// List the IBC channels of a deployment by querying the Hermes instance
-    IbcChannels(DeploymentArgs),
+    IbcChannels(IbcChannelsArgs),
 
-    // TODO: Dump the Namada ledger contents to a toml file
-    // DumpDb(DumpDbArgs),
+    /// Dump the Namada ledger contents (accounts, balances and bonds) and the genesis token configs to a toml file
+    DumpDb(DumpDbArgs),
 }
 
 #[derive(Args)]
@@ -47,6 +88,40 @@ pub struct CreateArgs {
     /// Directory where the deployment will be created
     #[arg(long)]
     deployment_dir: String,
+
+    /// Replace an existing deployment in the directory, as long as none of its containers are running
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args)]
+pub struct UpArgs {
+    /// Path to the deployment configuration TOML file
+    #[arg(long)]
+    config_file: String,
+
+    /// Directory where the deployment will be created; defaults to a new directory under the system temp dir
+    #[arg(long)]
+    deployment_dir: Option<String>,
+
+    /// How long to wait for the chains and IBC channels before giving up, eg. 90s, 20m or 1h30m
+    #[arg(long, default_value = "20m", value_parser = utils::parse_duration)]
+    timeout: Duration,
+
+    /// How often to check the chains and channels
+    #[arg(long, default_value = "10s", value_parser = utils::parse_duration)]
+    interval: Duration,
+
+    /// Keep the deployment when it fails to start or become ready, so it can be inspected
+    #[arg(long)]
+    keep_on_failure: bool,
+}
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    /// Path to the deployment configuration TOML file
+    #[arg(long)]
+    config_file: String,
 }
 
 #[derive(Args)]
@@ -56,23 +131,236 @@ pub struct DeploymentArgs {
     deployment_dir: String,
 }
 
+#[derive(Args)]
+pub struct StartArgs {
+    /// Directory of the deployment
+    #[arg(long)]
+    deployment_dir: String,
+
+    /// Only start this module (by name, or directory in the deployment); can be given more than once
+    #[arg(long = "module")]
+    modules: Vec<String>,
+
+    /// How long to wait for each module to become ready before the modules depending on it are given up on
+    #[arg(long, default_value = "5m", value_parser = utils::parse_duration)]
+    timeout: Duration,
+}
+
+#[derive(Args)]
+pub struct StopArgs {
+    /// Directory of the deployment
+    #[arg(long)]
+    deployment_dir: String,
+
+    /// Only stop this module (by name, or directory in the deployment); can be given more than once
+    #[arg(long = "module")]
+    modules: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct ResetArgs {
+    /// Directory of the deployment
+    #[arg(long)]
+    deployment_dir: String,
+
+    /// Only reset this module (by name, or directory in the deployment); can be given more than once. Hermes is
+    /// always reset
+    #[arg(long = "module")]
+    modules: Vec<String>,
+
+    /// Remove the resources without asking for confirmation
+    #[arg(long)]
+    yes: bool,
+
+    /// How long to wait for each module to become ready before the modules depending on it are given up on
+    #[arg(long, default_value = "5m", value_parser = utils::parse_duration)]
+    timeout: Duration,
+}
+
+#[derive(Args)]
+pub struct SnapshotArgs {
+    /// Directory of the deployment
+    #[arg(long)]
+    deployment_dir: String,
+
+    /// Name of the snapshot
+    #[arg(long)]
+    name: String,
+
+    /// Delete the snapshot and its images instead of taking it
+    #[arg(long)]
+    delete: bool,
+}
+
+#[derive(Args)]
+pub struct RestoreArgs {
+    /// Directory of the deployment
+    #[arg(long)]
+    deployment_dir: String,
+
+    /// Name of the snapshot
+    #[arg(long)]
+    name: String,
+
+    /// Replace the current state without asking for confirmation
+    #[arg(long)]
+    yes: bool,
+
+    /// How long to wait for each module to become ready before the modules depending on it are given up on
+    #[arg(long, default_value = "5m", value_parser = utils::parse_duration)]
+    timeout: Duration,
+}
+
+#[derive(Args)]
+pub struct CloneArgs {
+    /// Directory of the deployment to copy
+    #[arg(long)]
+    from: String,
+
+    /// Directory to create the copy in
+    #[arg(long)]
+    to: String,
+
+    /// How long to wait for each module to become ready before the modules depending on it are given up on
+    #[arg(long, default_value = "5m", value_parser = utils::parse_duration)]
+    timeout: Duration,
+}
+
+#[derive(Args)]
+pub struct EndpointsArgs {
+    /// Directory of the deployment
+    #[arg(long)]
+    deployment_dir: String,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = EndpointsFormat::Json)]
+    format: EndpointsFormat,
+
+    /// Prefix of the variable names in the env format, eg. NEXT_PUBLIC_ for Next.js
+    #[arg(long, default_value = "VITE_")]
+    env_prefix: String,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum EndpointsFormat {
+    Json,
+    /// `.env` file lines
+    Env,
+    Yaml,
+}
+
+#[derive(Args)]
+pub struct IbcChannelsArgs {
+    /// Directory of the deployment
+    #[arg(long)]
+    deployment_dir: String,
+
+    /// Output format; channels that have not been created yet are listed as pending
+    #[arg(long, value_enum, default_value_t = ChannelsOutput::Table)]
+    output: ChannelsOutput,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ChannelsOutput {
+    Json,
+    Yaml,
+    Table,
+}
+
+#[derive(Args)]
+pub struct CleanArgs {
+    /// Directory of the deployment
+    #[arg(long)]
+    deployment_dir: String,
+
+    /// Remove the resources without asking for confirmation
+    #[arg(long)]
+    yes: bool,
+
+    /// Also delete the deployment directory
+    #[arg(long)]
+    purge: bool,
+}
+
+#[derive(Args)]
+pub struct PruneArgs {
+    /// Only list the resources that would be removed
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Remove the resources without asking for confirmation
+    #[arg(long)]
+    yes: bool,
+}
+
+#[derive(Args)]
+pub struct StatusArgs {
+    /// Directory of the deployment
+    #[arg(long)]
+    deployment_dir: String,
+
+    /// Print the status as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+pub struct WaitArgs {
+    /// Directory of the deployment
+    #[arg(long)]
+    deployment_dir: String,
+
+    /// How long to wait before giving up, eg. 90s, 20m or 1h30m
+    #[arg(long, default_value = "20m", value_parser = utils::parse_duration)]
+    timeout: Duration,
+
+    /// How often to check the chains and channels
+    #[arg(long, default_value = "10s", value_parser = utils::parse_duration)]
+    interval: Duration,
+}
+
 #[derive(Args)]
 pub struct DumpDbArgs {
+    /// Directory of the deployment
+    #[arg(long)]
+    deployment_dir: String,
+
     /// Output file for the database dump (TOML format)
     #[arg(long)]
     output_file: String,
+
+    /// Name or directory of the Namada module within the deployment
+    #[arg(long, default_value = "namada")]
+    module: String,
 }
 
-fn main() -> Result<(), error::AppError> {
+fn main() {
     let cli = Cli::parse();
 
-    match cli.command {
+    let result = match cli.command {
         Commands::Create(args) => handle_create(args),
+        Commands::Validate(args) => handle_validate(args),
+        Commands::Up(args) => handle_up(args),
+        Commands::Down(args) => handle_down(args),
         Commands::Start(args) => handle_start(args),
         Commands::Stop(args) => handle_stop(args),
+        Commands::Restart(args) => handle_restart(args),
+        Commands::Reset(args) => handle_reset(args),
+        Commands::Snapshot(args) => handle_snapshot(args),
+        Commands::Restore(args) => handle_restore(args),
+        Commands::Clone(args) => handle_clone(args),
+        Commands::Endpoints(args) => handle_endpoints(args),
         Commands::Clean(args) => handle_clean(args),
+        Commands::Prune(args) => handle_prune(args),
+        Commands::Status(args) => handle_status(args),
         Commands::List(args) => handle_list(args),
         Commands::IbcChannels(args) => handle_ibc_channels(args),
-        // Commands::DumpDb(args) => handle_dump_db(args),
+        Commands::DumpDb(args) => handle_dump_db(args),
+        Commands::Wait(args) => handle_wait(args),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error: {:?}", err);
+        std::process::exit(err.exit_code());
     }
 }
@@ -1,33 +1,16 @@
-use std::path::{Path, PathBuf};
-use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::net::{SocketAddr, TcpStream};
 use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use crate::error::AppError;
 use colored::Colorize;
 
 pub const HERMES_TEMPLATE_DIR: &str = "hermes_templates";
 
-pub fn deployment_compose_files(dir: &Path) -> Result<Vec<PathBuf>, AppError> {
-    let mut compose_files = Vec::new();
-
-    for entry in fs::read_dir(dir).map_err(AppError::Io)? {
-        let entry = entry.map_err(AppError::Io)?;
-        let subfolder = entry.path();
-        if subfolder.is_dir() {
-            let compose_file = subfolder.join("docker-compose.yml");
-            if compose_file.exists() {
-                compose_files.push(compose_file);
-            }
-        }
-    }
+// Containers come first, as networks, volumes and images cannot be removed while containers use them
+pub const RESOURCE_TYPES: [&str; 4] = ["container", "network", "volume", "image"];
 
-    if compose_files.is_empty() {
-        return Err(AppError::InvalidConfig(
-            "No valid docker-compose.yml files found".into(),
-        ));
-    }
-
-    Ok(compose_files)
-}
+const HTTP_TIMEOUT: Duration = Duration::from_secs(3);
 
 pub fn list_resources(resource_type: &str, project_name: &str) -> Result<(), AppError> {
     println!("\n{}", format!("Deployment {}s:", resource_type).yellow().bold());
@@ -59,7 +42,7 @@ pub fn list_resources(resource_type: &str, project_name: &str) -> Result<(), App
 
     let resource_names_output = String::from_utf8_lossy(&resources_output.stdout);
     let resource_names = resource_names_output.lines().collect::<Vec<_>>();
-    if resource_names.len() == 0 {
+    if resource_names.is_empty() {
         println!("None");
         return Ok(());
     }
@@ -69,4 +52,268 @@ pub fn list_resources(resource_type: &str, project_name: &str) -> Result<(), App
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+/// Names of the containers, networks or volumes carrying a label (given as `key=value`)
+pub fn labeled_resources(resource_type: &str, label: &str) -> Result<Vec<String>, AppError> {
+    let name_format: &str = match resource_type {
+        "container" => "{{.Names}}",
+        "image" => "{{.Repository}}:{{.Tag}}",
+        _ => "{{.Name}}",
+    };
+    let mut ls_command = Command::new("docker");
+    ls_command.arg(resource_type).arg("ls");
+    if resource_type == "container" {
+        ls_command.arg("--all");
+    }
+    let resources_output = ls_command
+        .arg("--filter")
+        .arg(format!("label={}", label))
+        .arg("--format")
+        .arg(name_format)
+        .output()
+        .map_err(AppError::Io)?;
+
+    if !resources_output.status.success() {
+        return Err(AppError::DockerCommand(format!(
+            "Failed to list {}s with label {}: {}",
+            resource_type,
+            label,
+            String::from_utf8_lossy(&resources_output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&resources_output.stdout)
+        .lines()
+        .map(String::from)
+        .collect())
+}
+
+/// Remove containers, networks, volumes and images, given in the order of RESOURCE_TYPES. Returns the number of resources that
+/// could not be removed
+pub fn remove_resources<S: AsRef<str>>(resources: &[(&str, S)]) -> Result<usize, AppError> {
+    let mut failed = 0;
+    for (resource_type, name) in resources {
+        let mut rm_command = Command::new("docker");
+        rm_command.arg(resource_type).arg("rm");
+        // Force removal of running containers, along with their anonymous volumes
+        if *resource_type == "container" {
+            rm_command.arg("-f").arg("-v");
+        }
+        let status = rm_command.arg(name.as_ref()).status().map_err(AppError::Io)?;
+
+        if !status.success() {
+            println!("Failed to remove {}: {}", resource_type, name.as_ref());
+            failed += 1;
+        }
+    }
+    Ok(failed)
+}
+
+/// Ask a yes/no question on the terminal; anything but `y` or `yes` is a no
+pub fn confirm(prompt: &str) -> Result<bool, AppError> {
+    print!("{} [y/N] ", prompt);
+    io::stdout().flush().map_err(AppError::Io)?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).map_err(AppError::Io)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// The state of a container (eg. running or exited), followed by its health if it has a healthcheck, or None if there
+/// is no such container
+pub fn container_state(container: &str) -> Option<String> {
+    let output = Command::new("docker")
+        .arg("container")
+        .arg("inspect")
+        .arg("--format")
+        .arg("{{.State.Status}}{{if .State.Health}} ({{.State.Health.Status}}){{end}}")
+        .arg(container)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether a port on localhost accepts TCP connections
+pub fn port_open(port: u16) -> bool {
+    TcpStream::connect_timeout(&SocketAddr::from(([127, 0, 0, 1], port)), HTTP_TIMEOUT).is_ok()
+}
+
+/// Send a GET request to a port on localhost, returning the status code and body of the response
+pub fn http_get(port: u16, path: &str) -> Result<(u16, String), String> {
+    let address = SocketAddr::from(([127, 0, 0, 1], port));
+    let mut stream = TcpStream::connect_timeout(&address, HTTP_TIMEOUT).map_err(|err| err.to_string())?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT)).map_err(|err| err.to_string())?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT)).map_err(|err| err.to_string())?;
+
+    // HTTP/1.0 keeps servers from using chunked encoding, and the connection is closed after the response
+    write!(stream, "GET {} HTTP/1.0\r\nHost: 127.0.0.1:{}\r\n\r\n", path, port).map_err(|err| err.to_string())?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).map_err(|err| err.to_string())?;
+
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head
+        .strip_prefix("HTTP/")
+        .and_then(|head| head.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| "not an HTTP response".to_string())?;
+    Ok((status, body.to_string()))
+}
+
+/// Seconds elapsed since an RFC 3339 UTC timestamp such as `2025-01-02T03:04:05.123456Z`
+pub fn seconds_since(timestamp: &str) -> Option<i64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+    Some(now - unix_seconds(timestamp)?)
+}
+
+// Seconds since the unix epoch of an RFC 3339 UTC timestamp, ignoring fractions of a second
+fn unix_seconds(timestamp: &str) -> Option<i64> {
+    let (date, time) = timestamp.trim_end_matches('Z').split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>());
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let time = time.split('.').next()?;
+    let mut time = time.splitn(3, ':').map(|part| part.parse::<i64>());
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+    // Days since the unix epoch of a date in the proleptic Gregorian calendar
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Print rows as a table with left-aligned columns, under a bold header row
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| -> String {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", format_row(headers.to_vec()).bold());
+    for row in rows {
+        println!("{}", format_row(row.iter().map(String::as_str).collect()));
+    }
+}
+
+/// Parse a duration given as a number of seconds, or as a combination of hours, minutes and seconds such as `1h30m`
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(format!("invalid duration {}: unknown unit {}", value, c)),
+        };
+        let amount: u64 = number.parse().map_err(|_| format!("invalid duration {}", value))?;
+        seconds += amount * unit;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!("invalid duration {}: missing unit after {}", value, number));
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn unix_seconds_of_calendar_dates() {
+        assert_eq!(unix_seconds("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(unix_seconds("1969-12-31T23:59:59Z"), Some(-1));
+        // Leap days, including a century that is a leap year and one that is not
+        assert_eq!(unix_seconds("2000-03-01T00:00:00Z"), Some(951868800));
+        assert_eq!(unix_seconds("2024-02-29T12:34:56Z"), Some(1709210096));
+        assert_eq!(unix_seconds("2100-03-01T00:00:00Z"), Some(4107542400));
+        assert_eq!(unix_seconds("2025-01-02T03:04:05.123456Z"), Some(1735787045));
+        assert_eq!(unix_seconds("2025-01-02"), None);
+        assert_eq!(unix_seconds("2025-01-02Tnoon"), None);
+    }
+
+    #[test]
+    fn seconds_since_a_past_timestamp() {
+        let elapsed = seconds_since("2025-01-02T03:04:05Z").unwrap();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        assert!((now - 1735787045 - elapsed).abs() <= 1);
+    }
+
+    // Serve a single connection with a canned response, returning the port and the request that was received
+    fn serve_once(response: &'static str) -> (u16, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            let mut reader = io::BufReader::new(stream.try_clone().unwrap());
+            while reader.read_line(&mut request).unwrap() > 0 && !request.ends_with("\r\n\r\n") {}
+            stream.write_all(response.as_bytes()).unwrap();
+            request
+        });
+        (port, handle)
+    }
+
+    #[test]
+    fn http_get_returns_status_and_body() {
+        let (port, handle) = serve_once("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"result\":{}}");
+        assert_eq!(http_get(port, "/status"), Ok((200, "{\"result\":{}}".to_string())));
+        assert!(handle.join().unwrap().starts_with("GET /status HTTP/1.0\r\n"));
+
+        let (port, _) = serve_once("HTTP/1.0 503 Service Unavailable\r\n\r\n");
+        assert_eq!(http_get(port, "/health"), Ok((503, String::new())));
+    }
+
+    #[test]
+    fn http_get_rejects_other_protocols() {
+        let (port, _) = serve_once("SSH-2.0-OpenSSH_9.6\r\n");
+        assert_eq!(http_get(port, "/"), Err("not an HTTP response".to_string()));
+    }
+
+    #[test]
+    fn parse_duration_of_seconds_and_units() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("45s"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("2h5m10s"), Ok(Duration::from_secs(7510)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_invalid_values() {
+        assert_eq!(parse_duration("10d"), Err("invalid duration 10d: unknown unit d".to_string()));
+        assert_eq!(parse_duration("1h30"), Err("invalid duration 1h30: missing unit after 30".to_string()));
+        assert_eq!(parse_duration("m"), Err("invalid duration m".to_string()));
+        assert!(parse_duration("-5").is_err());
+    }
+}